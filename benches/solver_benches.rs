@@ -2,15 +2,19 @@ use criterion::{
     criterion_group, criterion_main, AxisScale, BenchmarkId, Criterion, PlotConfiguration,
 };
 use nonogram::{
-    generator::random_nonogram, solver::Solver, solver_backtrack::SolverBacktrack,
+    generator::{random_nonogram, seeded_rng},
+    solver::Solver,
+    solver_backtrack::SolverBacktrack,
     solver_backtrack_by_cell::SolverBacktrackByCell,
     solver_backtrack_inference::SolverBacktrackInference,
+    solver_line_propagation::SolverLinePropagation,
 };
 
 fn with_size(c: &mut Criterion, size: usize) {
     let plot_config = PlotConfiguration::default().summary_scale(AxisScale::Logarithmic);
     let mut group = c.benchmark_group(format!("Size = {}", size));
     group.plot_config(plot_config);
+    let mut rng = seeded_rng(size as u64);
     for density in [0.6, 0.633, 0.667, 0.7, 0.733, 0.767, 0.8] {
         if size < 20 {
             group.bench_with_input(
@@ -18,7 +22,7 @@ fn with_size(c: &mut Criterion, size: usize) {
                 &density,
                 |b, &density| {
                     b.iter(|| {
-                        let problem = random_nonogram(size, size, density);
+                        let problem = random_nonogram(size, size, density, &mut rng);
                         let mut solver = SolverBacktrack::new(&problem);
                         solver.any_solution();
                     })
@@ -30,7 +34,7 @@ fn with_size(c: &mut Criterion, size: usize) {
             &density,
             |b, &density| {
                 b.iter(|| {
-                    let problem = random_nonogram(size, size, density);
+                    let problem = random_nonogram(size, size, density, &mut rng);
                     let mut solver = SolverBacktrackInference::new(&problem);
                     solver.any_solution();
                 })
@@ -41,12 +45,23 @@ fn with_size(c: &mut Criterion, size: usize) {
             &density,
             |b, &density| {
                 b.iter(|| {
-                    let problem = random_nonogram(size, size, density);
+                    let problem = random_nonogram(size, size, density, &mut rng);
                     let mut solver = SolverBacktrackByCell::new(&problem);
                     solver.any_solution();
                 })
             },
         );
+        group.bench_with_input(
+            BenchmarkId::new("Line Propagation", density),
+            &density,
+            |b, &density| {
+                b.iter(|| {
+                    let problem = random_nonogram(size, size, density, &mut rng);
+                    let mut solver = SolverLinePropagation::new(&problem);
+                    solver.any_solution();
+                })
+            },
+        );
     }
 }
 
@@ -0,0 +1,267 @@
+// Runs a solver over a directory of puzzles, e.g. to compare solver versions
+// on a corpus. Every puzzle gets a row in the report, files that cannot be
+// read or hold invalid puzzles are reported with their error.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    puzzle_file::{is_puzzle_file, read_puzzle_file},
+    solver::Solver,
+};
+
+pub struct Batch {
+    // per puzzle
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchEntry {
+    // path relative to the directory with `/` separators, files with several
+    // puzzles get `#1`, `#2`, ... appended
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    pub time: Duration,
+    // a lower bound if `timed_out`
+    pub solution_cnt: u32,
+    // `None` if the solver timed out before it could tell
+    pub is_unique: Option<bool>,
+    pub timed_out: bool,
+    // the other fields are zero if set
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchReport {
+    // sorted by name
+    pub entries: Vec<BatchEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchSummary {
+    pub puzzles: usize,
+    pub unique: usize,
+    pub multiple: usize,
+    pub no_solution: usize,
+    pub timed_out: usize,
+    pub errors: usize,
+    pub time: Duration,
+}
+
+impl Default for Batch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self { timeout: None }
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Walks `dir` recursively, only files with one of `PUZZLE_EXTENSIONS` are
+    // read.
+    pub fn run<S: Solver>(&self, dir: &Path) -> io::Result<BatchReport> {
+        let mut files = vec![];
+        collect_files(dir, &mut files)?;
+        files.sort();
+
+        let mut entries = vec![];
+        for file in files {
+            let name = file
+                .strip_prefix(dir)
+                .unwrap_or(&file)
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            let puzzles = match read_puzzle_file(&file) {
+                Ok(puzzles) => puzzles,
+                Err(e) => {
+                    entries.push(BatchEntry::error(name, e.to_string()));
+                    continue;
+                }
+            };
+            if puzzles.is_empty() {
+                entries.push(BatchEntry::error(name, "no puzzle in the file".to_string()));
+                continue;
+            }
+            let several = puzzles.len() > 1;
+            for (idx, puzzle) in puzzles.into_iter().enumerate() {
+                let name = match several {
+                    true => format!("{}#{}", name, idx + 1),
+                    false => name.clone(),
+                };
+                let problem = match puzzle {
+                    Ok((problem, _)) => problem,
+                    Err(e) => {
+                        entries.push(BatchEntry::error(name, e.to_string()));
+                        continue;
+                    }
+                };
+                if let Err(e) = problem.validate() {
+                    entries.push(BatchEntry::error(name, e.to_string()));
+                    continue;
+                }
+
+                let mut solver = S::new(&problem);
+                if let Some(timeout) = self.timeout {
+                    solver.timeout(timeout);
+                }
+                let start = Instant::now();
+                let count = solver.solution_cnt();
+                let time = start.elapsed();
+                let (solution_cnt, timed_out) = (count.count, !count.is_exact);
+                let is_unique = match timed_out {
+                    true if solution_cnt >= 2 => Some(false),
+                    true => None,
+                    false => Some(solution_cnt == 1),
+                };
+                entries.push(BatchEntry {
+                    name,
+                    width: problem.width(),
+                    height: problem.height(),
+                    time,
+                    solution_cnt,
+                    is_unique,
+                    timed_out,
+                    error: None,
+                });
+            }
+        }
+        Ok(BatchReport { entries })
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if is_puzzle_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl BatchEntry {
+    fn error(name: String, error: String) -> Self {
+        Self {
+            name,
+            width: 0,
+            height: 0,
+            time: Duration::ZERO,
+            solution_cnt: 0,
+            is_unique: None,
+            timed_out: false,
+            error: Some(error),
+        }
+    }
+}
+
+impl BatchReport {
+    pub fn summary(&self) -> BatchSummary {
+        let cnt = |f: &dyn Fn(&BatchEntry) -> bool| self.entries.iter().filter(|e| f(e)).count();
+        BatchSummary {
+            puzzles: self.entries.len(),
+            unique: cnt(&|e| e.is_unique == Some(true)),
+            multiple: cnt(&|e| e.solution_cnt >= 2),
+            no_solution: cnt(&|e| e.error.is_none() && !e.timed_out && e.solution_cnt == 0),
+            timed_out: cnt(&|e| e.timed_out),
+            errors: cnt(&|e| e.error.is_some()),
+            time: self.entries.iter().map(|e| e.time).sum(),
+        }
+    }
+
+    // One row per puzzle with a header, times in milliseconds. Unknown
+    // uniqueness and missing errors are empty fields.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "name,width,height,time_ms,solution_cnt,unique,timed_out,error\n".to_string();
+        for entry in &self.entries {
+            csv += &format!(
+                "{},{},{},{:.3},{},{},{},{}\n",
+                csv_field(&entry.name),
+                entry.width,
+                entry.height,
+                entry.time.as_secs_f64() * 1000.0,
+                entry.solution_cnt,
+                entry.is_unique.map_or(String::new(), |u| u.to_string()),
+                entry.timed_out,
+                csv_field(entry.error.as_deref().unwrap_or("")),
+            );
+        }
+        csv
+    }
+
+    // `{"summary": {...}, "puzzles": [...]}` with the fields of `BatchSummary`
+    // and `BatchEntry`, unknown uniqueness and missing errors are `null`.
+    pub fn to_json(&self) -> String {
+        let summary = self.summary();
+        let mut json = "{\n  \"summary\": {".to_string();
+        json += &format!(
+            "\"puzzles\": {}, \"unique\": {}, \"multiple\": {}, \"no_solution\": {}, \
+             \"timed_out\": {}, \"errors\": {}, \"time_ms\": {:.3}",
+            summary.puzzles,
+            summary.unique,
+            summary.multiple,
+            summary.no_solution,
+            summary.timed_out,
+            summary.errors,
+            summary.time.as_secs_f64() * 1000.0
+        );
+        json += "},\n  \"puzzles\": [";
+        for (idx, entry) in self.entries.iter().enumerate() {
+            json += if idx == 0 { "\n" } else { ",\n" };
+            json += &format!(
+                "    {{\"name\": {}, \"width\": {}, \"height\": {}, \"time_ms\": {:.3}, \
+                 \"solution_cnt\": {}, \"unique\": {}, \"timed_out\": {}, \"error\": {}}}",
+                json_string(&entry.name),
+                entry.width,
+                entry.height,
+                entry.time.as_secs_f64() * 1000.0,
+                entry.solution_cnt,
+                entry
+                    .is_unique
+                    .map_or("null".to_string(), |u| u.to_string()),
+                entry.timed_out,
+                entry
+                    .error
+                    .as_deref()
+                    .map_or("null".to_string(), json_string),
+            );
+        }
+        json += "\n  ]\n}\n";
+        json
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    match text.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut json = "\"".to_string();
+    for c in text.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            c if (c as u32) < 0x20 => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json + "\""
+}
@@ -1,6 +1,53 @@
-pub type Line = u64;
+use std::ops::BitAndAssign;
 
-#[derive(Clone)]
+use crate::problem::CellState;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+// A line of cells packed into 64-bit words, so lines may be arbitrarily long.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Line(Vec<u64>);
+
+impl Line {
+    pub fn zeros(len: usize) -> Self {
+        Self(vec![0; len.div_ceil(WORD_BITS)])
+    }
+
+    // Bits past `len` are set as well; they are never read.
+    pub fn ones(len: usize) -> Self {
+        Self(vec![u64::MAX; len.div_ceil(WORD_BITS)])
+    }
+
+    pub fn get(&self, idx: usize) -> bool {
+        self.0[idx / WORD_BITS] & (1 << (idx % WORD_BITS)) != 0
+    }
+
+    pub fn set(&mut self, idx: usize) {
+        self.0[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
+    }
+
+    pub fn set_range(&mut self, start: usize, len: usize) {
+        for idx in start..start + len {
+            self.set(idx);
+        }
+    }
+
+    pub fn and_not_assign(&mut self, rhs: &Line) {
+        for (word, rhs_word) in self.0.iter_mut().zip(&rhs.0) {
+            *word &= !rhs_word;
+        }
+    }
+}
+
+impl BitAndAssign<&Line> for Line {
+    fn bitand_assign(&mut self, rhs: &Line) {
+        for (word, rhs_word) in self.0.iter_mut().zip(&rhs.0) {
+            *word &= rhs_word;
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct Domain(pub Vec<Line>);
 
 impl Domain {
@@ -15,9 +62,24 @@ impl Domain {
     pub fn size(&self) -> usize {
         self.0.len()
     }
+
+    // Drops every value that disagrees with a decided cell of `cells`.
+    pub fn restrict(&mut self, cells: &[CellState]) {
+        self.0.retain(|value| {
+            cells
+                .iter()
+                .enumerate()
+                .all(|(idx, cell)| cell.admits(value.get(idx)))
+        });
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
 pub enum VarType {
     Column,
     Row,
@@ -36,19 +98,22 @@ pub fn enumerate_domain(
         return;
     }
 
-    let bar_len = line_info[num_idx];
-    let bar = (1 << bar_len) - 1;
+    let bar_len = line_info[num_idx] as usize;
+    // cells needed by this bar and every bar after it
+    let rest_len =
+        line_info[num_idx..].iter().sum::<i32>() as usize + line_info.len() - num_idx - 1;
     for place_pos in start_pos..line_width {
-        if place_pos + bar_len as usize > line_width {
+        if place_pos + rest_len > line_width {
             break;
         }
 
-        let new_line = line | (bar << place_pos);
+        let mut new_line = line.clone();
+        new_line.set_range(place_pos, bar_len);
         enumerate_domain(
             line_info,
             num_idx + 1,
             line_width,
-            place_pos + bar_len as usize + 1,
+            place_pos + bar_len + 1,
             new_line,
             listed,
         );
@@ -60,11 +125,40 @@ fn test_enumerate_domain() {
     let mut domain = Domain::new();
     let line_info = vec![5, 3, 1];
     let line_width = 15;
-    enumerate_domain(&line_info, 0, line_width, 0, 0, &mut domain);
+    enumerate_domain(
+        &line_info,
+        0,
+        line_width,
+        0,
+        Line::zeros(line_width),
+        &mut domain,
+    );
     for line in domain.0 {
         for i in 0..line_width {
-            print!("{}", if line & (1 << i) != 0 { "🟩" } else { "⬛" });
+            print!("{}", if line.get(i) { "🟩" } else { "⬛" });
         }
         println!();
     }
 }
+
+#[test]
+fn test_enumerate_domain_long_line() {
+    let mut domain = Domain::new();
+    let line_info = vec![60, 30, 20];
+    let line_width = 112;
+    enumerate_domain(
+        &line_info,
+        0,
+        line_width,
+        0,
+        Line::zeros(line_width),
+        &mut domain,
+    );
+    assert_eq!(domain.size(), 1);
+    let line = &domain.0[0];
+    assert!((0..60).all(|i| line.get(i)));
+    assert!(!line.get(60));
+    assert!((61..91).all(|i| line.get(i)));
+    assert!(!line.get(91));
+    assert!((92..112).all(|i| line.get(i)));
+}
@@ -0,0 +1,316 @@
+// The `.non` text format:
+//
+//     title "Heart"
+//     width 5
+//     height 4
+//     rows
+//     1,1
+//     5
+//     3
+//     1
+//     columns
+//     1
+//     3
+//     3
+//     3
+//     1
+//     goal "01010111110111000100"
+//
+// Clue numbers are separated by commas or spaces, an empty line is written as
+// `0`. `goal` lists the solution row by row. Blank lines and unknown keywords
+// are ignored.
+
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use crate::{problem::Problem, solver::Solution};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NonError {
+    // 1-based line number and the offending text
+    InvalidNumber {
+        line: usize,
+        text: String,
+    },
+    InvalidClue {
+        line: usize,
+        text: String,
+    },
+    InvalidGoal {
+        line: usize,
+        text: String,
+    },
+    MissingSection(&'static str),
+    // `width`/`height` disagree with the number of clue lines
+    ClueCountMismatch {
+        section: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    // the goal does not satisfy the clues
+    WrongGoal,
+}
+
+impl Display for NonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NonError::InvalidNumber { line, text } => {
+                write!(f, "line {}: invalid number `{}`", line, text)
+            }
+            NonError::InvalidClue { line, text } => {
+                write!(f, "line {}: invalid clue `{}`", line, text)
+            }
+            NonError::InvalidGoal { line, text } => {
+                write!(f, "line {}: invalid goal `{}`", line, text)
+            }
+            NonError::MissingSection(section) => write!(f, "missing `{}` section", section),
+            NonError::ClueCountMismatch {
+                section,
+                expected,
+                found,
+            } => write!(
+                f,
+                "`{}` has {} clue lines, expected {}",
+                section, found, expected
+            ),
+            NonError::WrongGoal => write!(f, "goal does not match the clues"),
+        }
+    }
+}
+
+impl Error for NonError {}
+
+#[derive(Clone)]
+pub struct NonPuzzle {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub problem: Problem,
+    pub goal: Option<Solution>,
+}
+
+impl From<Problem> for NonPuzzle {
+    fn from(problem: Problem) -> Self {
+        Self {
+            title: None,
+            author: None,
+            problem,
+            goal: None,
+        }
+    }
+}
+
+impl From<Solution> for NonPuzzle {
+    fn from(solution: Solution) -> Self {
+        Self {
+            title: None,
+            author: None,
+            problem: solution.problem.clone(),
+            goal: Some(solution),
+        }
+    }
+}
+
+enum Section {
+    None,
+    Rows,
+    Columns,
+}
+
+impl FromStr for NonPuzzle {
+    type Err = NonError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut title = None;
+        let mut author = None;
+        let mut width = None;
+        let mut height = None;
+        let mut row_info = None;
+        let mut col_info = None;
+        let mut goal = None;
+        let mut section = Section::None;
+
+        for (idx, text) in input.lines().enumerate() {
+            let line = idx + 1;
+            let text = text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            if text.starts_with(|c: char| c.is_ascii_digit()) {
+                let clue = parse_clue(line, text)?;
+                match section {
+                    Section::Rows => row_info.get_or_insert_with(Vec::new).push(clue),
+                    Section::Columns => col_info.get_or_insert_with(Vec::new).push(clue),
+                    Section::None => {
+                        return Err(NonError::InvalidClue {
+                            line,
+                            text: text.to_string(),
+                        })
+                    }
+                }
+                continue;
+            }
+
+            section = Section::None;
+            let (keyword, value) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+            let value = value.trim();
+            match keyword {
+                "title" => title = Some(unquote(value).to_string()),
+                "by" => author = Some(unquote(value).to_string()),
+                "width" => width = Some(parse_number(line, value)?),
+                "height" => height = Some(parse_number(line, value)?),
+                "rows" => {
+                    section = Section::Rows;
+                    row_info.get_or_insert_with(Vec::new);
+                }
+                "columns" => {
+                    section = Section::Columns;
+                    col_info.get_or_insert_with(Vec::new);
+                }
+                "goal" => goal = Some((line, unquote(value).to_string())),
+                _ => {}
+            }
+        }
+
+        let row_info = row_info.ok_or(NonError::MissingSection("rows"))?;
+        let col_info = col_info.ok_or(NonError::MissingSection("columns"))?;
+        for (section, expected, found) in [
+            ("rows", height, row_info.len()),
+            ("columns", width, col_info.len()),
+        ] {
+            if let Some(expected) = expected {
+                if expected != found {
+                    return Err(NonError::ClueCountMismatch {
+                        section,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        let problem = Problem::from_clues(row_info, col_info);
+
+        let goal = match goal {
+            Some((line, text)) => {
+                let solution = parse_goal(&problem, line, &text)?;
+                if !solution.is_correct() {
+                    return Err(NonError::WrongGoal);
+                }
+                Some(solution)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            title,
+            author,
+            problem,
+            goal,
+        })
+    }
+}
+
+impl Display for NonPuzzle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(title) = &self.title {
+            writeln!(f, "title \"{}\"", quotable(title))?;
+        }
+        if let Some(author) = &self.author {
+            writeln!(f, "by \"{}\"", quotable(author))?;
+        }
+        writeln!(f, "width {}", self.problem.width())?;
+        writeln!(f, "height {}", self.problem.height())?;
+        writeln!(f)?;
+        writeln!(f, "rows")?;
+        for clue in &self.problem.row_info {
+            writeln!(f, "{}", format_clue(clue))?;
+        }
+        writeln!(f)?;
+        writeln!(f, "columns")?;
+        for clue in &self.problem.col_info {
+            writeln!(f, "{}", format_clue(clue))?;
+        }
+        if let Some(goal) = &self.goal {
+            writeln!(f)?;
+            write!(f, "goal \"")?;
+            for row in &goal.grid {
+                for cell in row {
+                    write!(f, "{}", if *cell { '1' } else { '0' })?;
+                }
+            }
+            writeln!(f, "\"")?;
+        }
+        Ok(())
+    }
+}
+
+// The format has no escapes. Quotes inside the value read back as they are,
+// since `unquote` only strips the outer pair, but a line break would end the
+// value, so control characters become spaces.
+fn quotable(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn parse_number(line: usize, text: &str) -> Result<usize, NonError> {
+    text.parse().map_err(|_| NonError::InvalidNumber {
+        line,
+        text: text.to_string(),
+    })
+}
+
+fn parse_clue(line: usize, text: &str) -> Result<Vec<i32>, NonError> {
+    let invalid = || NonError::InvalidClue {
+        line,
+        text: text.to_string(),
+    };
+    let nums = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|num| !num.is_empty())
+        .map(|num| num.parse::<i32>().map_err(|_| invalid()))
+        .collect::<Result<Vec<i32>, NonError>>()?;
+    match nums[..] {
+        [0] => Ok(vec![]),
+        _ if nums.iter().all(|num| *num > 0) => Ok(nums),
+        _ => Err(invalid()),
+    }
+}
+
+fn format_clue(clue: &[i32]) -> String {
+    if clue.is_empty() {
+        return "0".to_string();
+    }
+    clue.iter()
+        .map(|num| num.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_goal(problem: &Problem, line: usize, text: &str) -> Result<Solution, NonError> {
+    let (width, height) = (problem.width(), problem.height());
+    let cells: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if cells.len() != width * height || cells.iter().any(|c| *c != '0' && *c != '1') {
+        return Err(NonError::InvalidGoal {
+            line,
+            text: text.to_string(),
+        });
+    }
+    Ok(Solution {
+        problem: problem.clone(),
+        grid: (0..height)
+            .map(|row| {
+                cells[row * width..(row + 1) * width]
+                    .iter()
+                    .map(|c| *c == '1')
+                    .collect()
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,317 @@
+// The XML puzzle format (pbn) used by webpbn.com and many public collections:
+//
+//     <puzzleset>
+//      <puzzle type="grid" defaultcolor="black">
+//       <title>Heart</title>
+//       <color name="white" char=".">fff</color>
+//       <color name="black" char="X">000</color>
+//       <clues type="columns">
+//        <line><count>1</count></line>
+//        ...
+//       </clues>
+//       <clues type="rows">
+//        <line><count>1</count><count>1</count></line>
+//        ...
+//       </clues>
+//       <solution type="goal">
+//        <image>
+//        |.X.X.|
+//        ...
+//        </image>
+//       </solution>
+//      </puzzle>
+//     </puzzleset>
+//
+// Only black-and-white puzzles map to a `Problem`: every count has to be in
+// the default color.
+
+use std::{error::Error, fmt::Display};
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::{problem::Problem, solver::Solution};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XmlError {
+    // malformed XML, with the parser's message
+    Parse(String),
+    UnexpectedElement(String),
+    UnsupportedType(String),
+    // a count in a color other than the default one
+    MultipleColors(String),
+    InvalidCount(String),
+    MissingClues(&'static str),
+    InvalidImage(String),
+    // the solution image does not satisfy the clues
+    WrongSolution,
+}
+
+impl Display for XmlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            XmlError::Parse(message) => write!(f, "malformed XML: {}", message),
+            XmlError::UnexpectedElement(name) => write!(f, "unexpected element <{}>", name),
+            XmlError::UnsupportedType(puzzle_type) => {
+                write!(f, "unsupported puzzle type `{}`", puzzle_type)
+            }
+            XmlError::MultipleColors(color) => {
+                write!(f, "color `{}` in a black-and-white puzzle", color)
+            }
+            XmlError::InvalidCount(text) => write!(f, "invalid count `{}`", text),
+            XmlError::MissingClues(clues_type) => write!(f, "missing {} clues", clues_type),
+            XmlError::InvalidImage(reason) => write!(f, "invalid solution image: {}", reason),
+            XmlError::WrongSolution => write!(f, "solution does not match the clues"),
+        }
+    }
+}
+
+impl Error for XmlError {}
+
+#[derive(Clone)]
+pub struct XmlPuzzle {
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub problem: Problem,
+    pub solution: Option<Solution>,
+}
+
+impl From<Problem> for XmlPuzzle {
+    fn from(problem: Problem) -> Self {
+        Self {
+            id: None,
+            title: None,
+            author: None,
+            problem,
+            solution: None,
+        }
+    }
+}
+
+impl From<Solution> for XmlPuzzle {
+    fn from(solution: Solution) -> Self {
+        Self {
+            id: None,
+            title: None,
+            author: None,
+            problem: solution.problem.clone(),
+            solution: Some(solution),
+        }
+    }
+}
+
+// Every puzzle of a `<puzzleset>`, or the single puzzle of a `<puzzle>` root.
+// Collections mix in multicolor and other unsupported puzzles, so each puzzle
+// is read on its own, only a broken document fails as a whole.
+pub fn read_puzzles(xml: &str) -> Result<Vec<Result<XmlPuzzle, XmlError>>, XmlError> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let document =
+        Document::parse_with_options(xml, options).map_err(|e| XmlError::Parse(e.to_string()))?;
+    let root = document.root_element();
+    match root.tag_name().name() {
+        "puzzleset" => Ok(elements(root, "puzzle").map(read_puzzle).collect()),
+        "puzzle" => Ok(vec![read_puzzle(root)]),
+        name => Err(XmlError::UnexpectedElement(name.to_string())),
+    }
+}
+
+pub fn write_puzzles(puzzles: &[XmlPuzzle]) -> String {
+    let mut xml = String::new();
+    xml += "<?xml version=\"1.0\"?>\n";
+    xml += "<!DOCTYPE pbn SYSTEM \"https://webpbn.com/pbn-0.3.dtd\">\n";
+    xml += "<puzzleset>\n";
+    for puzzle in puzzles {
+        xml += " <puzzle type=\"grid\" defaultcolor=\"black\">\n";
+        for (tag, value) in [
+            ("id", &puzzle.id),
+            ("title", &puzzle.title),
+            ("author", &puzzle.author),
+        ] {
+            if let Some(value) = value {
+                xml += &format!("  <{}>{}</{}>\n", tag, escape(value), tag);
+            }
+        }
+        xml += "  <color name=\"white\" char=\".\">fff</color>\n";
+        xml += "  <color name=\"black\" char=\"X\">000</color>\n";
+        for (clues_type, info) in [
+            ("columns", &puzzle.problem.col_info),
+            ("rows", &puzzle.problem.row_info),
+        ] {
+            xml += &format!("  <clues type=\"{}\">\n", clues_type);
+            for line in info {
+                xml += "   <line>";
+                for num in line {
+                    xml += &format!("<count>{}</count>", num);
+                }
+                xml += "</line>\n";
+            }
+            xml += "  </clues>\n";
+        }
+        if let Some(solution) = &puzzle.solution {
+            xml += "  <solution type=\"goal\">\n   <image>\n";
+            for row in &solution.grid {
+                xml += "|";
+                for cell in row {
+                    xml.push(if *cell { 'X' } else { '.' });
+                }
+                xml += "|\n";
+            }
+            xml += "   </image>\n  </solution>\n";
+        }
+        xml += " </puzzle>\n";
+    }
+    xml += "</puzzleset>\n";
+    xml
+}
+
+fn elements<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child_text(node: Node, name: &'static str) -> Option<String> {
+    elements(node, name)
+        .next()
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+}
+
+fn read_puzzle(node: Node) -> Result<XmlPuzzle, XmlError> {
+    let puzzle_type = node.attribute("type").unwrap_or("grid");
+    if puzzle_type != "grid" {
+        return Err(XmlError::UnsupportedType(puzzle_type.to_string()));
+    }
+    let default_color = node.attribute("defaultcolor").unwrap_or("black");
+    let background_color = node.attribute("backgroundcolor").unwrap_or("white");
+
+    let mut col_info = None;
+    let mut row_info = None;
+    for clues in elements(node, "clues") {
+        let info = read_clues(clues, default_color)?;
+        match clues.attribute("type") {
+            Some("columns") => col_info = Some(info),
+            Some("rows") => row_info = Some(info),
+            _ => {}
+        }
+    }
+    let col_info = col_info.ok_or(XmlError::MissingClues("columns"))?;
+    let row_info = row_info.ok_or(XmlError::MissingClues("rows"))?;
+    let problem = Problem::from_clues(row_info, col_info);
+
+    // characters used in the solution image
+    let mut background_chars = vec!['.'];
+    let mut foreground_chars = vec!['X'];
+    for color in elements(node, "color") {
+        let Some(c) = color.attribute("char").and_then(|c| c.chars().next()) else {
+            continue;
+        };
+        match color.attribute("name") {
+            Some(name) if name == background_color => background_chars.push(c),
+            Some(name) if name == default_color => foreground_chars.push(c),
+            _ => {}
+        }
+    }
+
+    let image = elements(node, "solution")
+        .filter(|solution| {
+            matches!(
+                solution.attribute("type").unwrap_or("goal"),
+                "goal" | "solution"
+            )
+        })
+        .find_map(|solution| child_text(solution, "image"));
+    let solution = match image {
+        Some(image) => {
+            let solution = Solution {
+                problem: problem.clone(),
+                grid: read_image(&image, &problem, &background_chars, &foreground_chars)?,
+            };
+            if !solution.is_correct() {
+                return Err(XmlError::WrongSolution);
+            }
+            Some(solution)
+        }
+        None => None,
+    };
+
+    Ok(XmlPuzzle {
+        id: child_text(node, "id"),
+        title: child_text(node, "title"),
+        author: child_text(node, "author"),
+        problem,
+        solution,
+    })
+}
+
+fn read_clues(clues: Node, default_color: &str) -> Result<Vec<Vec<i32>>, XmlError> {
+    elements(clues, "line")
+        .map(|line| {
+            elements(line, "count")
+                .map(|count| {
+                    if let Some(color) = count.attribute("color") {
+                        if color != default_color {
+                            return Err(XmlError::MultipleColors(color.to_string()));
+                        }
+                    }
+                    let text = count.text().unwrap_or("").trim();
+                    match text.parse::<i32>() {
+                        Ok(num) if num > 0 => Ok(num),
+                        _ => Err(XmlError::InvalidCount(text.to_string())),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn read_image(
+    image: &str,
+    problem: &Problem,
+    background_chars: &[char],
+    foreground_chars: &[char],
+) -> Result<Vec<Vec<bool>>, XmlError> {
+    let grid = image
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let cells = line
+                .strip_prefix('|')
+                .and_then(|line| line.strip_suffix('|'))
+                .ok_or_else(|| {
+                    XmlError::InvalidImage(format!("row `{}` is not framed by |", line))
+                })?;
+            cells
+                .chars()
+                .map(|c| {
+                    if foreground_chars.contains(&c) {
+                        Ok(true)
+                    } else if background_chars.contains(&c) {
+                        Ok(false)
+                    } else {
+                        Err(XmlError::InvalidImage(format!("unknown character `{}`", c)))
+                    }
+                })
+                .collect::<Result<Vec<bool>, XmlError>>()
+        })
+        .collect::<Result<Vec<Vec<bool>>, XmlError>>()?;
+    if grid.len() != problem.height() || grid.iter().any(|row| row.len() != problem.width()) {
+        return Err(XmlError::InvalidImage(
+            "size does not match the clues".to_string(),
+        ));
+    }
+    Ok(grid)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
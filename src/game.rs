@@ -0,0 +1,224 @@
+// The state of a puzzle being played: the board, a cursor and the history of
+// moves. Frontends like the terminal game only translate keys into these calls
+// and draw the result.
+
+use std::time::Duration;
+
+use crate::{
+    csp::VarType,
+    hint::next_hint,
+    problem::{CellState, Problem, ProblemError},
+    solver::{Outcome, PartialGrid, Solver},
+    solver_line_propagation::SolverLinePropagation,
+};
+
+// for hints and verification that need a full solve
+const SOLVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub struct Game {
+    pub board: PartialGrid,
+    // (row, column)
+    pub cursor: (usize, usize),
+    // every move is a list of (row, column, before, after), a hint can change
+    // several cells at once
+    undo: Vec<Vec<(usize, usize, CellState, CellState)>>,
+    redo: Vec<Vec<(usize, usize, CellState, CellState)>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineStatus {
+    Open,
+    // the filled cells match the clue, the rest can be crossed out
+    Complete,
+    // the decided cells contradict the clue
+    Violated,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Solved,
+    Violated(Vec<(VarType, usize)>),
+    // no line is violated on its own, but the board has a mistake
+    NoSolution,
+    // the board can still be completed
+    Consistent,
+    // the solver could not tell in time
+    TimedOut,
+    // the problem itself is broken
+    Invalid(ProblemError),
+}
+
+impl Game {
+    pub fn new(problem: &Problem) -> Self {
+        let mut problem = problem.clone();
+        problem.canonicalize();
+        Self {
+            board: PartialGrid::new(&problem),
+            cursor: (0, 0),
+            undo: vec![],
+            redo: vec![],
+        }
+    }
+
+    // Stops at the edges of the board.
+    pub fn move_cursor(&mut self, rows: isize, cols: isize) {
+        let (height, width) = (self.board.problem.height(), self.board.problem.width());
+        let clamp = |pos: usize, delta: isize, len: usize| {
+            pos.saturating_add_signed(delta).min(len.saturating_sub(1))
+        };
+        self.cursor = (
+            clamp(self.cursor.0, rows, height),
+            clamp(self.cursor.1, cols, width),
+        );
+    }
+
+    // Sets the cell under the cursor, or clears it if it is in `state` already.
+    pub fn toggle(&mut self, state: CellState) {
+        let (row, col) = self.cursor;
+        let Some(&current) = self.board.grid.get(row).and_then(|cells| cells.get(col)) else {
+            return;
+        };
+        let state = match current == state {
+            true => CellState::Unknown,
+            false => state,
+        };
+        self.apply(vec![(row, col, state)]);
+    }
+
+    // One undoable move, cells that already are in their state are skipped.
+    pub fn apply(&mut self, cells: Vec<(usize, usize, CellState)>) {
+        let changes: Vec<_> = cells
+            .into_iter()
+            .filter(|(row, col, state)| self.board.grid[*row][*col] != *state)
+            .map(|(row, col, state)| (row, col, self.board.grid[row][col], state))
+            .collect();
+        if changes.is_empty() {
+            return;
+        }
+        for (row, col, _, after) in &changes {
+            self.board.grid[*row][*col] = *after;
+        }
+        self.undo.push(changes);
+        self.redo.clear();
+    }
+
+    // `false` if there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(changes) = self.undo.pop() else {
+            return false;
+        };
+        for (row, col, before, _) in &changes {
+            self.board.grid[*row][*col] = *before;
+        }
+        self.redo.push(changes);
+        true
+    }
+
+    // `false` if there is nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(changes) = self.redo.pop() else {
+            return false;
+        };
+        for (row, col, _, after) in &changes {
+            self.board.grid[*row][*col] = *after;
+        }
+        self.undo.push(changes);
+        true
+    }
+
+    // The status of every row and every column.
+    pub fn line_status(&self) -> (Vec<LineStatus>, Vec<LineStatus>) {
+        let problem = &self.board.problem;
+        let status = |info: &[i32], line: Vec<CellState>| -> LineStatus {
+            if blocks(&line) == info {
+                LineStatus::Complete
+            } else {
+                LineStatus::Open
+            }
+        };
+        let mut rows: Vec<LineStatus> = (0..problem.height())
+            .map(|row| status(&problem.row_info[row], self.board.row(row)))
+            .collect();
+        let mut cols: Vec<LineStatus> = (0..problem.width())
+            .map(|col| status(&problem.col_info[col], self.board.col(col)))
+            .collect();
+        for (var_type, idx) in self.board.conflicting_lines() {
+            match var_type {
+                VarType::Row => rows[idx] = LineStatus::Violated,
+                VarType::Column => cols[idx] = LineStatus::Violated,
+            }
+        }
+        (rows, cols)
+    }
+
+    // Deduces the next cells like `next_hint` does and fills them in as one
+    // move. When no single line helps, the solver reveals the cell under the
+    // cursor, or the first unknown one. Returns the explanation, `None` if the
+    // board has a mistake, is complete or the solver timed out.
+    pub fn hint(&mut self) -> Option<String> {
+        if let Some(hint) = next_hint(&self.board) {
+            self.apply(hint.cells);
+            return Some(hint.reason);
+        }
+        let mut solver = SolverLinePropagation::new(&self.board.problem);
+        solver.timeout(SOLVER_TIMEOUT).partial_grid(&self.board);
+        let solution = solver.any_solution().into_solution()?;
+        let cursor = [self.cursor].into_iter();
+        let unknown = (0..self.board.grid.len())
+            .flat_map(|row| (0..self.board.grid[row].len()).map(move |col| (row, col)));
+        let (row, col) = cursor.chain(unknown).find(|(row, col)| {
+            self.board.grid.get(*row).and_then(|cells| cells.get(*col)) == Some(&CellState::Unknown)
+        })?;
+        let state = match solution.grid[row][col] {
+            true => CellState::Filled,
+            false => CellState::Empty,
+        };
+        self.apply(vec![(row, col, state)]);
+        Some(format!(
+            "row {}, column {}: the solver says it is {}",
+            row + 1,
+            col + 1,
+            match state {
+                CellState::Filled => "filled",
+                _ => "empty",
+            }
+        ))
+    }
+
+    pub fn verify(&self) -> Verdict {
+        let violated = self.board.conflicting_lines();
+        if !violated.is_empty() {
+            return Verdict::Violated(violated);
+        }
+        if self
+            .board
+            .to_solution()
+            .is_some_and(|solution| solution.is_correct())
+        {
+            return Verdict::Solved;
+        }
+        let mut solver = SolverLinePropagation::new(&self.board.problem);
+        solver.timeout(SOLVER_TIMEOUT).partial_grid(&self.board);
+        match solver.any_solution() {
+            Outcome::Solved(_) | Outcome::Multiple(_) => Verdict::Consistent,
+            Outcome::NoSolution => Verdict::NoSolution,
+            Outcome::TimedOut { .. } | Outcome::Cancelled { .. } => Verdict::TimedOut,
+            Outcome::Invalid(err) => Verdict::Invalid(err),
+        }
+    }
+}
+
+// Lengths of the runs of filled cells.
+fn blocks(line: &[CellState]) -> Vec<i32> {
+    let mut blocks = vec![];
+    let mut run = 0;
+    for cell in line.iter().chain([CellState::Empty].iter()) {
+        if *cell == CellState::Filled {
+            run += 1;
+        } else if run > 0 {
+            blocks.push(run);
+            run = 0;
+        }
+    }
+    blocks
+}
@@ -1,16 +1,38 @@
-use rand::random;
+use std::time::{Duration, Instant};
+
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 use crate::{
-    problem::Problem, solver::Solver, solver_backtrack_by_cell::SolverBacktrackByCell,
+    grader::{Difficulty, Grader},
+    problem::Problem,
+    solver::{Outcome, Solver},
+    solver_backtrack_by_cell::SolverBacktrackByCell,
     solver_backtrack_inference::SolverBacktrackInference,
 };
 
-pub fn random_nonogram(width: usize, height: usize, density: f64) -> Problem {
+// The same seed yields the same puzzles on every run and platform, as long as
+// the random numbers are drawn through `random_index` and `gen::<f64>`.
+pub fn seeded_rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+// `gen_range` on `usize` depends on the pointer width, so draw a `u64`.
+fn random_index<R: Rng + ?Sized>(rng: &mut R, len: usize) -> usize {
+    rng.gen_range(0..len as u64) as usize
+}
+
+pub fn random_nonogram<R: Rng + ?Sized>(
+    width: usize,
+    height: usize,
+    density: f64,
+    rng: &mut R,
+) -> Problem {
     let mut grid = vec![vec![false; width]; height];
 
     for row in &mut grid {
         for cell in row {
-            if random::<f64>() < density {
+            if rng.gen::<f64>() < density {
                 *cell = true;
             }
         }
@@ -19,32 +41,41 @@ pub fn random_nonogram(width: usize, height: usize, density: f64) -> Problem {
     Problem::from(grid)
 }
 
-pub fn random_nonogram_with_unique_solution(
+// Clears random cells of a completely filled grid for as long as the solution
+// stays unique and the density stays above `expected_density`. If uniqueness
+// is lost too early the expected density is raised a little and the grid
+// starts over, so the result is never sparser than requested.
+pub fn random_nonogram_with_unique_solution<R: Rng + ?Sized>(
     width: usize,
     height: usize,
-    mut expected_density: f64,
+    expected_density: f64,
+    rng: &mut R,
 ) -> Problem {
     let mut grid = vec![vec![true; width]; height];
+    if width == 0 || height == 0 {
+        return Problem::from(grid);
+    }
+    let mut expected_density = expected_density.clamp(0.0, 1.0);
     let mut rest = width * height;
     loop {
         rest -= 1;
-        let (mut r, mut c) = (random::<usize>() % height, random::<usize>() % width);
-        while !grid[c][r] {
-            (r, c) = (random::<usize>() % height, random::<usize>() % width);
+        let (mut r, mut c) = (random_index(rng, height), random_index(rng, width));
+        while !grid[r][c] {
+            (r, c) = (random_index(rng, height), random_index(rng, width));
         }
 
-        grid[c][r] = false;
+        grid[r][c] = false;
         let problem = Problem::from(grid.clone());
         let density = rest as f64 / (width * height) as f64;
-        if if width * height > 360 && density > 0.7 {
-            SolverBacktrackByCell::new(&problem)
-                .unique_solution()
-                .is_unique
-        } else {
-            SolverBacktrackInference::new(&problem)
-                .unique_solution()
-                .is_unique
-        } && density > expected_density
+        if density > expected_density
+            && matches!(
+                if width * height > 360 && density > 0.7 {
+                    SolverBacktrackByCell::new(&problem).unique_solution()
+                } else {
+                    SolverBacktrackInference::new(&problem).unique_solution()
+                },
+                Outcome::Solved(_)
+            )
         {
             continue;
         }
@@ -57,7 +88,140 @@ pub fn random_nonogram_with_unique_solution(
             continue;
         }
 
-        grid[c][r] = true;
+        grid[r][c] = true;
         return Problem::from(grid);
     }
 }
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeneratorConfig {
+    pub width: usize,
+    pub height: usize,
+    pub density: f64,
+    pub min_difficulty: Difficulty,
+    pub max_difficulty: Difficulty,
+    // fresh random grids to start from
+    pub max_attempts: u32,
+    // cell flips tried on each grid before starting over
+    pub max_mutations: u32,
+    // reproducible output, see `seeded_rng`
+    pub seed: Option<u64>,
+    // for the whole search, grading included. Running out gives `None`
+    // rather than a different puzzle, so a seeded run yields its puzzle or
+    // nothing.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub timeout: Option<Duration>,
+}
+
+impl GeneratorConfig {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            density: 0.5,
+            min_difficulty: Difficulty::Easy,
+            max_difficulty: Difficulty::Expert,
+            max_attempts: 100,
+            max_mutations: (width * height) as u32,
+            seed: None,
+            timeout: None,
+        }
+    }
+
+    pub fn density(&mut self, density: f64) -> &mut Self {
+        self.density = density;
+        self
+    }
+
+    pub fn difficulty(&mut self, difficulty: Difficulty) -> &mut Self {
+        self.min_difficulty = difficulty;
+        self.max_difficulty = difficulty;
+        self
+    }
+
+    pub fn difficulty_range(&mut self, min: Difficulty, max: Difficulty) -> &mut Self {
+        self.min_difficulty = min;
+        self.max_difficulty = max;
+        self
+    }
+
+    pub fn max_attempts(&mut self, max_attempts: u32) -> &mut Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    pub fn max_mutations(&mut self, max_mutations: u32) -> &mut Self {
+        self.max_mutations = max_mutations;
+        self
+    }
+
+    pub fn seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // A problem with a unique solution whose graded difficulty lies within
+    // the configured range. Random grids are mutated one cell at a time until
+    // one fits. `None` if every attempt or the timeout is used up, the grader
+    // only gives up on its branch limit otherwise.
+    pub fn generate(&self) -> Option<Problem> {
+        match self.seed {
+            Some(seed) => self.generate_with_rng(&mut seeded_rng(seed)),
+            None => self.generate_with_rng(&mut thread_rng()),
+        }
+    }
+
+    // Ignores `seed`.
+    pub fn generate_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Problem> {
+        if self.width == 0 || self.height == 0 {
+            return None;
+        }
+        let deadline = self
+            .timeout
+            .and_then(|timeout| Instant::now().checked_add(timeout));
+        for _ in 0..self.max_attempts {
+            let mut grid = vec![vec![false; self.width]; self.height];
+            for row in &mut grid {
+                for cell in row {
+                    *cell = rng.gen::<f64>() < self.density;
+                }
+            }
+            for _ in 0..=self.max_mutations {
+                let problem = Problem::from(grid.clone());
+                let mut grader = Grader::new();
+                if let Some(deadline) = deadline {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return None;
+                    }
+                    grader.timeout(left);
+                }
+                let grade = grader.grade(&problem);
+                // a grade cut short by the clock would depend on the machine
+                if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                    return None;
+                }
+                if let Some(grade) = grade {
+                    // the grader gave up, the solution may not even be unique
+                    if grade.difficulty != Difficulty::TooHard
+                        && (self.min_difficulty..=self.max_difficulty).contains(&grade.difficulty)
+                    {
+                        return Some(problem);
+                    }
+                }
+                let (r, c) = (
+                    random_index(rng, self.height),
+                    random_index(rng, self.width),
+                );
+                grid[r][c] = !grid[r][c];
+            }
+        }
+        None
+    }
+}
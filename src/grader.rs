@@ -0,0 +1,320 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    hint::Technique,
+    line_solver::{solve_line, solve_line_overlap},
+    problem::{CellState, Problem},
+    solver::{CancelToken, PartialGrid},
+};
+
+const DEFAULT_BRANCH_LIMIT: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Difficulty {
+    // overlap alone solves it
+    Easy,
+    // needs full line solving
+    Medium,
+    // needs probing for contradictions
+    Hard,
+    // needs guessing
+    Expert,
+    // the grader gave up on its limits, not even uniqueness is known
+    #[cfg_attr(feature = "serde", serde(rename = "too_hard"))]
+    TooHard,
+}
+
+impl From<Technique> for Difficulty {
+    fn from(technique: Technique) -> Self {
+        match technique {
+            Technique::Overlap => Difficulty::Easy,
+            Technique::LineSolving => Difficulty::Medium,
+            Technique::Probing => Difficulty::Hard,
+            Technique::Backtracking => Difficulty::Expert,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Grade {
+    pub difficulty: Difficulty,
+    // weighted sum of `steps`, see `technique_weight`
+    pub score: u32,
+    // every technique used with how many cells it decided, or how many
+    // guesses were made for `Technique::Backtracking`
+    pub steps: Vec<(Technique, u32)>,
+    pub branches: u32,
+}
+
+impl Grade {
+    pub fn techniques(&self) -> Vec<Technique> {
+        self.steps.iter().map(|(technique, _)| *technique).collect()
+    }
+}
+
+pub fn technique_weight(technique: Technique) -> u32 {
+    match technique {
+        Technique::Overlap => 1,
+        Technique::LineSolving => 3,
+        Technique::Probing => 10,
+        Technique::Backtracking => 50,
+    }
+}
+
+// `Grader` with the default limits.
+pub fn grade(problem: &Problem) -> Option<Grade> {
+    Grader::new().grade(problem)
+}
+
+// Guessing can take exponential time, so the grader gives up with
+// `Difficulty::TooHard` once any of its limits runs out.
+pub struct Grader {
+    // guesses while backtracking
+    branch_limit: u32,
+    timeout: Option<Duration>,
+    cancel: CancelToken,
+}
+
+// What is left of the limits during one `Grader::grade`.
+struct Budget {
+    branch_limit: u32,
+    deadline: Option<Instant>,
+    cancel: CancelToken,
+}
+
+impl Budget {
+    fn is_exhausted(&self, branches: u32) -> bool {
+        branches >= self.branch_limit
+            || self.cancel.is_cancelled()
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() > deadline)
+    }
+}
+
+impl Default for Grader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Grader {
+    pub fn new() -> Self {
+        Self {
+            branch_limit: DEFAULT_BRANCH_LIMIT,
+            timeout: None,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    pub fn branch_limit(&mut self, branch_limit: u32) -> &mut Self {
+        self.branch_limit = branch_limit;
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    // Solves the problem the way a person would, always falling back to the
+    // simplest technique that still makes progress: overlap, then line
+    // solving, then probing single cells, then guessing. `None` iff the
+    // problem is invalid or does not have a unique solution, as far as the
+    // grader got before giving up.
+    pub fn grade(&self, problem: &Problem) -> Option<Grade> {
+        let budget = Budget {
+            branch_limit: self.branch_limit,
+            // too far out to represent is as good as none
+            deadline: self
+                .timeout
+                .and_then(|timeout| Instant::now().checked_add(timeout)),
+            cancel: self.cancel.clone(),
+        };
+        grade_within(problem, &budget)
+    }
+}
+
+fn grade_within(problem: &Problem, budget: &Budget) -> Option<Grade> {
+    let mut problem = problem.clone();
+    problem.canonicalize();
+    problem.validate().ok()?;
+    let mut grid = PartialGrid::new(&problem);
+    let mut steps: Vec<(Technique, u32)> = vec![];
+    let mut record =
+        |technique: Technique, cnt: u32| match steps.iter_mut().find(|(t, _)| *t == technique) {
+            Some((_, total)) => *total += cnt,
+            None => steps.push((technique, cnt)),
+        };
+    let mut branches = 0;
+    let mut gave_up = false;
+
+    loop {
+        if grid.is_complete() {
+            if !grid.is_consistent() {
+                return None;
+            }
+            break;
+        }
+        let decided = line_pass(&mut grid, Technique::Overlap)?;
+        if decided > 0 {
+            record(Technique::Overlap, decided);
+            continue;
+        }
+        let decided = line_pass(&mut grid, Technique::LineSolving)?;
+        if decided > 0 {
+            record(Technique::LineSolving, decided);
+            continue;
+        }
+        let decided = probe(&mut grid, budget)?;
+        if decided > 0 {
+            record(Technique::Probing, decided);
+            continue;
+        }
+
+        let mut solution_cnt = 0;
+        gave_up = !backtrack(grid.clone(), &mut solution_cnt, &mut branches, budget);
+        if !gave_up && solution_cnt != 1 {
+            return None;
+        }
+        if branches > 0 {
+            record(Technique::Backtracking, branches);
+        }
+        break;
+    }
+
+    steps.sort();
+    let difficulty = match gave_up {
+        true => Difficulty::TooHard,
+        false => steps.last().map_or(Difficulty::Easy, |(technique, _)| {
+            Difficulty::from(*technique)
+        }),
+    };
+    let score = steps
+        .iter()
+        .map(|(technique, cnt)| technique_weight(*technique) * cnt)
+        .sum();
+    Some(Grade {
+        difficulty,
+        score,
+        steps,
+        branches,
+    })
+}
+
+// Applies `technique` once to every row and column. Number of newly decided
+// cells, `None` on a contradiction.
+fn line_pass(grid: &mut PartialGrid, technique: Technique) -> Option<u32> {
+    let solve = match technique {
+        Technique::Overlap => solve_line_overlap,
+        _ => solve_line,
+    };
+    let mut decided = 0;
+    for row in 0..grid.problem.height() {
+        let solved = solve(&grid.problem.row_info[row], &grid.row(row))?;
+        for (col, cell) in solved.into_iter().enumerate() {
+            if grid.grid[row][col] != cell {
+                grid.grid[row][col] = cell;
+                decided += 1;
+            }
+        }
+    }
+    for col in 0..grid.problem.width() {
+        let solved = solve(&grid.problem.col_info[col], &grid.col(col))?;
+        for (row, cell) in solved.into_iter().enumerate() {
+            if grid.grid[row][col] != cell {
+                grid.grid[row][col] = cell;
+                decided += 1;
+            }
+        }
+    }
+    Some(decided)
+}
+
+// Line solving until nothing changes. `false` on a contradiction.
+fn propagate(grid: &mut PartialGrid) -> bool {
+    loop {
+        match line_pass(grid, Technique::LineSolving) {
+            None => return false,
+            Some(0) => return true,
+            Some(_) => {}
+        }
+    }
+}
+
+// Decides the first unknown cell whose opposite value leads line solving into
+// a contradiction. Number of decided cells, `None` if both values do. Stops
+// with 0 once the budget runs out, `backtrack` then gives up right away.
+fn probe(grid: &mut PartialGrid, budget: &Budget) -> Option<u32> {
+    for r in 0..grid.grid.len() {
+        for c in 0..grid.grid[r].len() {
+            if grid.grid[r][c] != CellState::Unknown {
+                continue;
+            }
+            if budget.is_exhausted(0) {
+                return Some(0);
+            }
+            let mut possible = vec![];
+            for value in [CellState::Filled, CellState::Empty] {
+                let mut trial = grid.clone();
+                trial.grid[r][c] = value;
+                if propagate(&mut trial) {
+                    possible.push(value);
+                }
+            }
+            match possible[..] {
+                [] => return None,
+                [value] => {
+                    grid.grid[r][c] = value;
+                    return Some(1);
+                }
+                _ => {}
+            }
+        }
+    }
+    Some(0)
+}
+
+// Stops as soon as a second solution turns up. `false` if the budget ran out
+// first.
+fn backtrack(
+    mut grid: PartialGrid,
+    solution_cnt: &mut u32,
+    branches: &mut u32,
+    budget: &Budget,
+) -> bool {
+    if budget.is_exhausted(*branches) {
+        return false;
+    }
+    if !propagate(&mut grid) {
+        return true;
+    }
+    let unknown = grid.grid.iter().enumerate().find_map(|(r, row)| {
+        row.iter()
+            .position(|cell| *cell == CellState::Unknown)
+            .map(|c| (r, c))
+    });
+    let Some((r, c)) = unknown else {
+        *solution_cnt += 1;
+        return true;
+    };
+    for value in [CellState::Filled, CellState::Empty] {
+        *branches += 1;
+        let mut trial = grid.clone();
+        trial.grid[r][c] = value;
+        if !backtrack(trial, solution_cnt, branches, budget) {
+            return false;
+        }
+        if *solution_cnt >= 2 {
+            return true;
+        }
+    }
+    true
+}
@@ -0,0 +1,181 @@
+use std::fmt::Display;
+
+use crate::{
+    csp::VarType,
+    line_solver::{solve_line, solve_line_overlap},
+    problem::CellState,
+    solver::PartialGrid,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Technique {
+    // leftmost/rightmost packing of the bars of a single line
+    Overlap,
+    // every arrangement of a single line that fits its decided cells
+    LineSolving,
+    // assuming a cell and following line solving into a contradiction
+    Probing,
+    // guessing cells and searching for every solution
+    Backtracking,
+}
+
+#[derive(Debug, Clone)]
+pub struct Hint {
+    // (row, column, deduced state)
+    pub cells: Vec<(usize, usize, CellState)>,
+    pub line: (VarType, usize),
+    pub technique: Technique,
+    pub reason: String,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+// The next cells that can be deduced from a single row or column, using the
+// simplest technique that makes progress. Among lines solved by the same
+// technique the one deducing the most cells wins. `None` if no line yields
+// anything new or the board already contradicts a clue.
+pub fn next_hint(grid: &PartialGrid) -> Option<Hint> {
+    if !grid.is_consistent() {
+        return None;
+    }
+    for technique in [Technique::Overlap, Technique::LineSolving] {
+        let best = lines(grid)
+            .filter_map(|(var_type, var_idx)| line_hint(grid, technique, var_type, var_idx))
+            .max_by_key(|hint| hint.cells.len());
+        if best.is_some() {
+            return best;
+        }
+    }
+    None
+}
+
+fn lines(grid: &PartialGrid) -> impl Iterator<Item = (VarType, usize)> {
+    (0..grid.problem.height())
+        .map(|row| (VarType::Row, row))
+        .chain((0..grid.problem.width()).map(|col| (VarType::Column, col)))
+}
+
+fn line_hint(
+    grid: &PartialGrid,
+    technique: Technique,
+    var_type: VarType,
+    var_idx: usize,
+) -> Option<Hint> {
+    let (line_info, line) = match var_type {
+        VarType::Row => (&grid.problem.row_info[var_idx], grid.row(var_idx)),
+        VarType::Column => (&grid.problem.col_info[var_idx], grid.col(var_idx)),
+    };
+    let solved = match technique {
+        Technique::Overlap => solve_line_overlap(line_info, &line)?,
+        Technique::LineSolving => solve_line(line_info, &line)?,
+        // a single line never needs these
+        Technique::Probing | Technique::Backtracking => return None,
+    };
+
+    let mut filled = vec![];
+    let mut empty = vec![];
+    let mut cells = vec![];
+    for (idx, (before, after)) in line.iter().zip(&solved).enumerate() {
+        if before == after {
+            continue;
+        }
+        match after {
+            CellState::Filled => filled.push(idx),
+            CellState::Empty => empty.push(idx),
+            CellState::Unknown => continue,
+        }
+        cells.push(match var_type {
+            VarType::Row => (var_idx, idx, *after),
+            VarType::Column => (idx, var_idx, *after),
+        });
+    }
+    if cells.is_empty() {
+        return None;
+    }
+
+    let line_name = match var_type {
+        VarType::Row => format!("Row {}", var_idx + 1),
+        VarType::Column => format!("Column {}", var_idx + 1),
+    };
+    let clue = if line_info.is_empty() {
+        "0".to_string()
+    } else {
+        line_info
+            .iter()
+            .map(|num| num.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let mut reason = format!("{} (clue {}): ", line_name, clue);
+    match technique {
+        Technique::Overlap => {
+            reason += "with every block pushed as far left and as far right as it goes";
+            if !filled.is_empty() {
+                reason += &format!(
+                    ", both positions of a block cover {}, which must be filled",
+                    describe_positions(&filled)
+                );
+            }
+            if !empty.is_empty() {
+                reason += &format!(
+                    "{} no block can reach {}, which must be empty",
+                    if filled.is_empty() { "," } else { ";" },
+                    describe_positions(&empty)
+                );
+            }
+        }
+        Technique::LineSolving => {
+            reason += "every way to place the blocks that fits the marked cells";
+            if !filled.is_empty() {
+                reason += &format!(" fills {}", describe_positions(&filled));
+            }
+            if !empty.is_empty() {
+                reason += &format!(
+                    "{} leaves {} empty",
+                    if filled.is_empty() { "" } else { " and" },
+                    describe_positions(&empty)
+                );
+            }
+        }
+        Technique::Probing | Technique::Backtracking => unreachable!(),
+    }
+    reason += ".";
+
+    Some(Hint {
+        cells,
+        line: (var_type, var_idx),
+        technique,
+        reason,
+    })
+}
+
+// "cell 3", "cells 1-3, 7" (1-based)
+fn describe_positions(positions: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for pos in positions {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *pos => *end = *pos,
+            _ => ranges.push((*pos, *pos)),
+        }
+    }
+    let list = ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("{}", start + 1)
+            } else {
+                format!("{}-{}", start + 1, end + 1)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if positions.len() == 1 {
+        format!("cell {}", list)
+    } else {
+        format!("cells {}", list)
+    }
+}
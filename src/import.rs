@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use crate::{
+    problem::Problem,
+    raster::Image,
+    solver::{Outcome, Solution, Solver},
+    solver_line_propagation::SolverLinePropagation,
+};
+
+// Turns pixel art into a puzzle: dark pixels become filled cells.
+pub struct ImageImporter {
+    // pixels (or averaged blocks) darker than this are filled
+    threshold: u8,
+    // target grid size in cells, the image's size if `None`
+    size: Option<(usize, usize)>,
+    // for the uniqueness check
+    timeout: Option<Duration>,
+}
+
+pub struct ImportedPuzzle {
+    pub problem: Problem,
+    pub solution: Solution,
+    // `None` if the check timed out before it could tell
+    pub is_unique: Option<bool>,
+}
+
+impl Default for ImageImporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ImageImporter {
+    pub fn new() -> Self {
+        Self {
+            threshold: 128,
+            size: None,
+            timeout: None,
+        }
+    }
+
+    pub fn threshold(&mut self, threshold: u8) -> &mut Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn size(&mut self, width: usize, height: usize) -> &mut Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    // Downsampling averages the block of pixels that falls into each cell.
+    pub fn grid(&self, image: &Image) -> Vec<Vec<bool>> {
+        let (image_width, image_height) = (image.width as usize, image.height as usize);
+        let (width, height) = self.size.unwrap_or((image_width, image_height));
+        if image_width == 0 || image_height == 0 {
+            return vec![vec![false; width]; height];
+        }
+        // pixel range covered by cell `idx` out of `cnt`, never empty
+        let span = |idx: usize, cnt: usize, len: usize| {
+            let start = (idx * len / cnt).min(len - 1);
+            start..((idx + 1) * len / cnt).max(start + 1)
+        };
+        (0..height)
+            .map(|r| {
+                let rows = span(r, height, image_height);
+                (0..width)
+                    .map(|c| {
+                        let cols = span(c, width, image_width);
+                        let cnt = rows.len() * cols.len();
+                        let sum: usize = rows
+                            .clone()
+                            .flat_map(|y| cols.clone().map(move |x| (x, y)))
+                            .map(|(x, y)| image.get(x as u32, y as u32) as usize)
+                            .sum();
+                        sum < self.threshold as usize * cnt
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn import(&self, image: &Image) -> ImportedPuzzle {
+        let grid = self.grid(image);
+        let problem = match grid.is_empty() {
+            true => Problem::empty(self.size.map_or(image.width as usize, |s| s.0), 0),
+            false => Problem::from(grid.clone()),
+        };
+        let mut solver = SolverLinePropagation::new(&problem);
+        if let Some(timeout) = self.timeout {
+            solver.timeout(timeout);
+        }
+        let is_unique = match solver.unique_solution() {
+            Outcome::Solved(_) => Some(true),
+            // a problem read off a grid is always valid
+            Outcome::NoSolution | Outcome::Multiple(_) | Outcome::Invalid(_) => Some(false),
+            Outcome::TimedOut { .. } | Outcome::Cancelled { .. } => None,
+        };
+        ImportedPuzzle {
+            solution: Solution {
+                problem: problem.clone(),
+                grid,
+            },
+            problem,
+            is_unique,
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::problem::Problem;
+
+// Geometry of a printed puzzle, shared by the renderers. Every clue number
+// takes one cell: row clues right of the grid, column clues below it, like in
+// the `Display` impls. All coordinates are in pixels from the top left corner
+// of the image.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub cell_size: u32,
+    pub margin: u32,
+    // grid size in cells
+    pub width: usize,
+    pub height: usize,
+    // clue cells right of / below the grid
+    pub row_clue_cells: usize,
+    pub col_clue_cells: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClueLabel {
+    // top left corner of the clue's cell
+    pub x: u32,
+    pub y: u32,
+    pub num: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridLine {
+    pub x1: u32,
+    pub y1: u32,
+    pub x2: u32,
+    pub y2: u32,
+    // the outline and every fifth line
+    pub bold: bool,
+}
+
+impl Layout {
+    pub fn new(problem: &Problem, cell_size: u32) -> Self {
+        let max_len = |info: &Vec<Vec<i32>>| info.iter().map(|line| line.len()).max().unwrap_or(0);
+        Self {
+            cell_size,
+            margin: cell_size / 2,
+            width: problem.width(),
+            height: problem.height(),
+            row_clue_cells: max_len(&problem.row_info).max(1),
+            col_clue_cells: max_len(&problem.col_info).max(1),
+        }
+    }
+
+    pub fn grid_left(&self) -> u32 {
+        self.margin
+    }
+
+    pub fn grid_top(&self) -> u32 {
+        self.margin
+    }
+
+    pub fn grid_right(&self) -> u32 {
+        self.grid_left() + self.width as u32 * self.cell_size
+    }
+
+    pub fn grid_bottom(&self) -> u32 {
+        self.grid_top() + self.height as u32 * self.cell_size
+    }
+
+    pub fn image_width(&self) -> u32 {
+        self.grid_right() + self.row_clue_cells as u32 * self.cell_size + self.margin
+    }
+
+    pub fn image_height(&self) -> u32 {
+        self.grid_bottom() + self.col_clue_cells as u32 * self.cell_size + self.margin
+    }
+
+    // Top left corner of a grid cell.
+    pub fn cell(&self, row: usize, col: usize) -> (u32, u32) {
+        (
+            self.grid_left() + col as u32 * self.cell_size,
+            self.grid_top() + row as u32 * self.cell_size,
+        )
+    }
+
+    // Clue numbers start next to the grid, empty lines get no label.
+    pub fn clue_labels(&self, problem: &Problem) -> Vec<ClueLabel> {
+        let mut labels = vec![];
+        for (col, line) in problem.col_info.iter().enumerate() {
+            let (x, _) = self.cell(0, col);
+            for (idx, num) in line.iter().enumerate() {
+                labels.push(ClueLabel {
+                    x,
+                    y: self.grid_bottom() + idx as u32 * self.cell_size,
+                    num: *num,
+                });
+            }
+        }
+        for (row, line) in problem.row_info.iter().enumerate() {
+            let (_, y) = self.cell(row, 0);
+            for (idx, num) in line.iter().enumerate() {
+                labels.push(ClueLabel {
+                    x: self.grid_right() + idx as u32 * self.cell_size,
+                    y,
+                    num: *num,
+                });
+            }
+        }
+        labels
+    }
+
+    pub fn grid_lines(&self) -> Vec<GridLine> {
+        let (left, top) = (self.grid_left(), self.grid_top());
+        let (right, bottom) = (self.grid_right(), self.grid_bottom());
+        let vertical = (0..=self.width).map(|col| GridLine {
+            x1: left + col as u32 * self.cell_size,
+            y1: top,
+            x2: left + col as u32 * self.cell_size,
+            y2: bottom,
+            bold: col % 5 == 0 || col == self.width,
+        });
+        let horizontal = (0..=self.height).map(|row| GridLine {
+            x1: left,
+            y1: top + row as u32 * self.cell_size,
+            x2: right,
+            y2: top + row as u32 * self.cell_size,
+            bold: row % 5 == 0 || row == self.height,
+        });
+        vertical.chain(horizontal).collect()
+    }
+}
@@ -1,9 +1,26 @@
+pub mod batch;
 pub mod csp;
+pub mod format_non;
+pub mod format_xml;
+pub mod game;
 pub mod generator;
+pub mod grader;
+pub mod hint;
+pub mod import;
+pub mod layout;
+pub mod line_solver;
+pub mod pdf;
 pub mod problem;
+pub mod puzzle_file;
+pub mod raster;
+pub mod render_raster;
+pub mod render_svg;
 pub mod solver;
 pub mod solver_backtrack;
 pub mod solver_backtrack_by_cell;
 pub mod solver_backtrack_inference;
+pub mod solver_line_propagation;
 #[cfg(test)]
 pub mod test;
+#[cfg(feature = "tui")]
+pub mod tui;
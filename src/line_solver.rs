@@ -0,0 +1,336 @@
+use crate::problem::CellState;
+
+// Start position of every bar in the leftmost placement that agrees with the
+// known cells of `line`, or `None` if there is no such placement.
+pub fn leftmost_placement(line_info: &[i32], line: &[CellState]) -> Option<Vec<usize>> {
+    let mut starts = vec![0; line_info.len()];
+    let mut failed = vec![false; (line_info.len() + 1) * (line.len() + 2)];
+    if place_bars(line_info, line, 0, 0, &mut starts, &mut failed) {
+        Some(starts)
+    } else {
+        None
+    }
+}
+
+// Start position of every bar in the rightmost placement that agrees with the
+// known cells of `line`, or `None` if there is no such placement.
+pub fn rightmost_placement(line_info: &[i32], line: &[CellState]) -> Option<Vec<usize>> {
+    let reversed_info: Vec<i32> = line_info.iter().rev().copied().collect();
+    let reversed_line: Vec<CellState> = line.iter().rev().copied().collect();
+    let reversed_starts = leftmost_placement(&reversed_info, &reversed_line)?;
+    Some(
+        reversed_starts
+            .iter()
+            .rev()
+            .zip(line_info)
+            .map(|(start, bar_len)| line.len() - start - *bar_len as usize)
+            .collect(),
+    )
+}
+
+fn place_bars(
+    line_info: &[i32],
+    line: &[CellState],
+    num_idx: usize,
+    start_pos: usize,
+    starts: &mut [usize],
+    failed: &mut [bool],
+) -> bool {
+    let line_width = line.len();
+    let start_pos = start_pos.min(line_width);
+    if num_idx >= line_info.len() {
+        return line[start_pos..].iter().all(|c| *c != CellState::Filled);
+    }
+    let memo_idx = num_idx * (line_width + 2) + start_pos;
+    if failed[memo_idx] {
+        return false;
+    }
+
+    let bar_len = line_info[num_idx] as usize;
+    for place_pos in start_pos..line_width {
+        // a filled cell may not be skipped by the bar
+        if place_pos > start_pos && line[place_pos - 1] == CellState::Filled {
+            break;
+        }
+        if place_pos + bar_len > line_width {
+            break;
+        }
+        if line[place_pos..place_pos + bar_len].contains(&CellState::Empty) {
+            continue;
+        }
+        if place_pos + bar_len < line_width && line[place_pos + bar_len] == CellState::Filled {
+            continue;
+        }
+
+        if place_bars(
+            line_info,
+            line,
+            num_idx + 1,
+            place_pos + bar_len + 1,
+            starts,
+            failed,
+        ) {
+            starts[num_idx] = place_pos;
+            return true;
+        }
+    }
+
+    failed[memo_idx] = true;
+    false
+}
+
+// Left-right overlap ("simple boxes" and "simple spaces"): cells covered by a
+// bar in both its leftmost and rightmost position are filled, cells that no
+// bar can reach are empty. `None` iff the line contradicts its clue.
+pub fn solve_line_overlap(line_info: &[i32], line: &[CellState]) -> Option<Vec<CellState>> {
+    let left = leftmost_placement(line_info, line)?;
+    let right = rightmost_placement(line_info, line)?;
+
+    let mut result = line.to_vec();
+    let mut reachable = vec![false; line.len()];
+    for (num_idx, bar_len) in line_info.iter().enumerate() {
+        let bar_len = *bar_len as usize;
+        if right[num_idx] < left[num_idx] + bar_len {
+            result[right[num_idx]..left[num_idx] + bar_len].fill(CellState::Filled);
+        }
+        reachable[left[num_idx]..right[num_idx] + bar_len].fill(true);
+    }
+    for (cell, reach) in result.iter_mut().zip(reachable) {
+        if !reach {
+            *cell = CellState::Empty;
+        }
+    }
+    Some(result)
+}
+
+// `can_place[i][j]`: the first `i` cells can hold exactly the first `j` bars
+// without contradicting the known cells.
+fn prefix_placements(line_info: &[i32], line: &[CellState]) -> Vec<Vec<bool>> {
+    let line_width = line.len();
+    // empty_cnt[i]: number of known empty cells among the first `i`
+    let mut empty_cnt = vec![0; line_width + 1];
+    for (i, cell) in line.iter().enumerate() {
+        empty_cnt[i + 1] = empty_cnt[i] + (*cell == CellState::Empty) as usize;
+    }
+
+    let mut can_place = vec![vec![false; line_info.len() + 1]; line_width + 1];
+    can_place[0][0] = true;
+    for i in 1..=line_width {
+        for j in 0..=line_info.len() {
+            if line[i - 1] != CellState::Filled && can_place[i - 1][j] {
+                can_place[i][j] = true;
+                continue;
+            }
+            if j == 0 {
+                continue;
+            }
+            let bar_len = line_info[j - 1] as usize;
+            if bar_len > i || empty_cnt[i] != empty_cnt[i - bar_len] {
+                continue;
+            }
+            let bar_start = i - bar_len;
+            can_place[i][j] = if bar_start == 0 {
+                j == 1
+            } else {
+                line[bar_start - 1] != CellState::Filled && can_place[bar_start - 1][j - 1]
+            };
+        }
+    }
+    can_place
+}
+
+// Every cell that is the same in all placements agreeing with the known cells
+// of `line`, computed in O(n·k) without enumerating placements. `None` iff the
+// line contradicts its clue.
+pub fn solve_line(line_info: &[i32], line: &[CellState]) -> Option<Vec<CellState>> {
+    let line_width = line.len();
+    let bar_cnt = line_info.len();
+    let prefix = prefix_placements(line_info, line);
+    if !prefix[line_width][bar_cnt] {
+        return None;
+    }
+    let reversed_info: Vec<i32> = line_info.iter().rev().copied().collect();
+    let reversed_line: Vec<CellState> = line.iter().rev().copied().collect();
+    let reversed_prefix = prefix_placements(&reversed_info, &reversed_line);
+    // the cells from `i` on can hold exactly the bars from `j` on
+    let suffix = |i: usize, j: usize| reversed_prefix[line_width - i][bar_cnt - j];
+
+    let mut can_be_empty = vec![false; line_width];
+    for (i, cell) in line.iter().enumerate() {
+        can_be_empty[i] =
+            *cell != CellState::Filled && (0..=bar_cnt).any(|j| prefix[i][j] && suffix(i + 1, j));
+    }
+
+    let mut empty_cnt = vec![0; line_width + 1];
+    for (i, cell) in line.iter().enumerate() {
+        empty_cnt[i + 1] = empty_cnt[i] + (*cell == CellState::Empty) as usize;
+    }
+    // difference array over the cells covered by some valid bar position
+    let mut covered = vec![0i32; line_width + 1];
+    for (j, bar_len) in line_info.iter().enumerate() {
+        let bar_len = *bar_len as usize;
+        for bar_start in 0..line_width.saturating_sub(bar_len) + 1 {
+            let bar_end = bar_start + bar_len;
+            if bar_end > line_width || empty_cnt[bar_end] != empty_cnt[bar_start] {
+                continue;
+            }
+            let before_ok = if bar_start == 0 {
+                j == 0
+            } else {
+                line[bar_start - 1] != CellState::Filled && prefix[bar_start - 1][j]
+            };
+            let after_ok = if bar_end == line_width {
+                j + 1 == bar_cnt
+            } else {
+                line[bar_end] != CellState::Filled && suffix(bar_end + 1, j + 1)
+            };
+            if before_ok && after_ok {
+                covered[bar_start] += 1;
+                covered[bar_end] -= 1;
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(line_width);
+    let mut cover_cnt = 0;
+    for i in 0..line_width {
+        cover_cnt += covered[i];
+        result.push(match (cover_cnt > 0, can_be_empty[i]) {
+            (true, true) => CellState::Unknown,
+            (true, false) => CellState::Filled,
+            (false, true) => CellState::Empty,
+            (false, false) => return None,
+        });
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::random;
+
+    use super::*;
+    use crate::{
+        csp::{enumerate_domain, Domain, Line},
+        problem::CellState::{Empty as E, Filled as F, Unknown as U},
+    };
+
+    #[test]
+    fn test_overlap_empty_line() {
+        assert_eq!(
+            solve_line_overlap(&[8], &[U; 10]),
+            Some(vec![U, U, F, F, F, F, F, F, U, U])
+        );
+        assert_eq!(
+            solve_line_overlap(&[3, 1], &[U; 5]),
+            Some(vec![F, F, F, E, F])
+        );
+        assert_eq!(solve_line_overlap(&[], &[U; 3]), Some(vec![E, E, E]));
+        assert_eq!(solve_line_overlap(&[1, 1, 1], &[U; 50]), Some(vec![U; 50]));
+    }
+
+    #[test]
+    fn test_overlap_known_cells() {
+        assert_eq!(
+            solve_line_overlap(&[2], &[U, U, F, U, U, U]),
+            Some(vec![E, U, F, U, E, E])
+        );
+        assert_eq!(
+            solve_line_overlap(&[1, 2], &[U, E, U, U, U, U, F]),
+            Some(vec![U, E, U, U, E, F, F])
+        );
+    }
+
+    #[test]
+    fn test_overlap_contradiction() {
+        assert_eq!(solve_line_overlap(&[3], &[U, E, U, E, U]), None);
+        assert_eq!(solve_line_overlap(&[1], &[F, E, F]), None);
+        assert_eq!(solve_line_overlap(&[], &[U, F]), None);
+    }
+
+    #[test]
+    fn test_solve_line() {
+        assert_eq!(
+            solve_line(&[1, 2], &[U, E, U, U, U, U, F]),
+            Some(vec![U, E, U, U, E, F, F])
+        );
+        // overlap alone cannot see that a filled 1 is surrounded by empty cells
+        assert_eq!(
+            solve_line(&[1, 1], &[U, U, F, U, U]),
+            Some(vec![U, E, F, E, U])
+        );
+        assert_eq!(
+            solve_line_overlap(&[1, 1], &[U, U, F, U, U]),
+            Some(vec![U, U, F, U, U])
+        );
+        assert_eq!(solve_line(&[], &[U, U]), Some(vec![E, E]));
+        assert_eq!(solve_line(&[2], &[U, F, E, F]), None);
+        assert_eq!(solve_line(&[1, 1], &[]), None);
+    }
+
+    #[test]
+    fn test_solve_line_agrees_with_enumeration() {
+        for _ in 0..1000 {
+            let line_width = random::<usize>() % 12 + 1;
+            let mut line_info = vec![];
+            let mut rest = line_width as i32;
+            while rest > 0 && random::<f64>() < 0.7 {
+                let bar_len = random::<i32>().rem_euclid(rest) + 1;
+                line_info.push(bar_len);
+                rest -= bar_len + 1;
+            }
+            let line: Vec<CellState> = (0..line_width)
+                .map(|_| match random::<u32>() % 6 {
+                    0 => F,
+                    1 => E,
+                    _ => U,
+                })
+                .collect();
+
+            let mut domain = Domain::new();
+            enumerate_domain(
+                &line_info,
+                0,
+                line_width,
+                0,
+                Line::zeros(line_width),
+                &mut domain,
+            );
+            let consistent: Vec<&Line> = domain
+                .0
+                .iter()
+                .filter(|value| {
+                    (0..line_width).all(|i| match line[i] {
+                        F => value.get(i),
+                        E => !value.get(i),
+                        U => true,
+                    })
+                })
+                .collect();
+            let expected = if consistent.is_empty() {
+                None
+            } else {
+                Some(
+                    (0..line_width)
+                        .map(|i| {
+                            if consistent.iter().all(|value| value.get(i)) {
+                                F
+                            } else if consistent.iter().all(|value| !value.get(i)) {
+                                E
+                            } else {
+                                U
+                            }
+                        })
+                        .collect::<Vec<_>>(),
+                )
+            };
+            assert_eq!(
+                solve_line(&line_info, &line),
+                expected,
+                "{:?} {:?}",
+                line_info,
+                line
+            );
+        }
+    }
+}
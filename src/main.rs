@@ -1,45 +1,519 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use nonogram::{
-    generator::{random_nonogram, random_nonogram_with_unique_solution},
-    solver::Solver,
+    batch::Batch,
+    format_non::NonPuzzle,
+    format_xml::{write_puzzles, XmlPuzzle},
+    generator::{random_nonogram, random_nonogram_with_unique_solution, seeded_rng},
+    pdf::Booklet,
+    problem::Problem,
+    puzzle_file::read_puzzle_file,
+    render_raster::RasterRenderer,
+    render_svg::SvgRenderer,
+    solver::{Outcome, Solution, Solver},
+    solver_backtrack::SolverBacktrack,
+    solver_backtrack_by_cell::SolverBacktrackByCell,
     solver_backtrack_inference::SolverBacktrackInference,
+    solver_line_propagation::SolverLinePropagation,
 };
+use rand::{thread_rng, Rng};
+
+// Exit codes besides 0 for success. Usage errors exit with 2 as well.
+const EXIT_NEGATIVE: u8 = 1;
+const EXIT_ERROR: u8 = 2;
+const EXIT_TIMEOUT: u8 = 3;
+
+/// Solve, generate, check and render nonograms.
+///
+/// Puzzles are read from `.non`, XML (`.xml`, `.pbn`) and black-and-white
+/// image files (`.pbm`, `.pgm`, `.png` with the `png` feature). Exit codes: 0
+/// on success, 1 if there is no solution (`solve`) or it is not unique
+/// (`check`) or the game was quit unsolved (`play`), 2 on errors and 3 on
+/// timeouts.
+#[derive(Parser)]
+#[command(name = "nonogram", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print a solution or count the solutions.
+    Solve {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = SolverKind::Propagation)]
+        solver: SolverKind,
+        /// Give up after this many seconds.
+        #[arg(long)]
+        timeout: Option<f64>,
+        /// Count all solutions instead of printing one.
+        #[arg(long)]
+        count: bool,
+    },
+    /// Print a random puzzle.
+    Generate {
+        #[arg(long)]
+        width: usize,
+        #[arg(long)]
+        height: usize,
+        /// Fraction of filled cells.
+        #[arg(long, default_value_t = 0.5)]
+        density: f64,
+        /// Makes the output reproducible.
+        #[arg(long)]
+        seed: Option<u64>,
+        /// Only puzzles with exactly one solution.
+        #[arg(long)]
+        unique: bool,
+        #[arg(long, value_enum, default_value_t = PuzzleFormat::Non)]
+        format: PuzzleFormat,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Tell whether the puzzle has exactly one solution.
+    Check {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = SolverKind::Propagation)]
+        solver: SolverKind,
+        /// Give up after this many seconds.
+        #[arg(long)]
+        timeout: Option<f64>,
+    },
+    /// Draw the puzzle, or its solution with `--solution`.
+    Render {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ImageFormat::Terminal)]
+        format: ImageFormat,
+        #[arg(long)]
+        solution: bool,
+        /// In pixels.
+        #[arg(long, default_value_t = 20)]
+        cell_size: u32,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Play the puzzle in the terminal.
+    #[cfg(feature = "tui")]
+    Play { file: PathBuf },
+    /// Solve every puzzle in a directory and report the results.
+    Batch {
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = SolverKind::Propagation)]
+        solver: SolverKind,
+        /// Give up on a puzzle after this many seconds.
+        #[arg(long)]
+        timeout: Option<f64>,
+        #[arg(long, value_enum, default_value_t = ReportFormat::Csv)]
+        format: ReportFormat,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Print puzzles and their answers as a PDF booklet.
+    Booklet(BookletArgs),
+}
+
+#[derive(Args)]
+struct BookletArgs {
+    /// Every puzzle in these files, before the generated ones.
+    files: Vec<PathBuf>,
+    /// Add this many random puzzles with exactly one solution.
+    #[arg(long, default_value_t = 0)]
+    generate: usize,
+    #[arg(long, default_value_t = 10)]
+    width: usize,
+    #[arg(long, default_value_t = 10)]
+    height: usize,
+    /// Fraction of filled cells in generated puzzles.
+    #[arg(long, default_value_t = 0.5)]
+    density: f64,
+    /// Makes the generated puzzles reproducible.
+    #[arg(long)]
+    seed: Option<u64>,
+    #[arg(long)]
+    title: Option<String>,
+    /// Give up solving a puzzle for the answer key after this many seconds.
+    #[arg(long)]
+    timeout: Option<f64>,
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SolverKind {
+    Backtrack,
+    Inference,
+    ByCell,
+    Propagation,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PuzzleFormat {
+    Non,
+    Xml,
+    // compact string for sharing
+    Code,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ImageFormat {
+    Terminal,
+    Svg,
+    Pbm,
+    Pgm,
+    #[cfg(feature = "png")]
+    Png,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Solve {
+            file,
+            solver,
+            timeout,
+            count,
+        } => solve(&file, solver, timeout, count),
+        Command::Generate {
+            width,
+            height,
+            density,
+            seed,
+            unique,
+            format,
+            output,
+        } => generate(width, height, density, seed, unique, format, output),
+        Command::Check {
+            file,
+            solver,
+            timeout,
+        } => check(&file, solver, timeout),
+        Command::Render {
+            file,
+            format,
+            solution,
+            cell_size,
+            output,
+        } => render(&file, format, solution, cell_size, output),
+        Command::Batch {
+            dir,
+            solver,
+            timeout,
+            format,
+            output,
+        } => batch(&dir, solver, timeout, format, output),
+        Command::Booklet(args) => booklet(args),
+        #[cfg(feature = "tui")]
+        Command::Play { file } => play(&file),
+    };
+    match result {
+        Ok(code) => ExitCode::from(code),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+// The first supported puzzle in the file. Images come with their picture as
+// the solution.
+fn read_puzzle(path: &Path) -> Result<(Problem, Option<Solution>), Box<dyn Error>> {
+    let puzzles = read_puzzle_file(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut first_error = None;
+    for puzzle in puzzles {
+        match puzzle {
+            Ok(puzzle) => return Ok(puzzle),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(match first_error {
+        Some(e) => format!("{}: {}", path.display(), e),
+        None => format!("{}: no puzzle in the file", path.display()),
+    }
+    .into())
+}
+
+fn write_output(output: Option<PathBuf>, bytes: &[u8]) -> Result<(), Box<dyn Error>> {
+    match output {
+        Some(path) => fs::write(&path, bytes).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => io::stdout().write_all(bytes)?,
+    }
+    Ok(())
+}
+
+fn timeout_duration(timeout: Option<f64>) -> Result<Option<Duration>, Box<dyn Error>> {
+    timeout
+        .map(|secs| Duration::try_from_secs_f64(secs).map_err(|_| "invalid timeout".into()))
+        .transpose()
+}
+
+fn new_solver<S: Solver>(problem: &Problem, timeout: Option<Duration>) -> S {
+    let mut solver = S::new(problem);
+    if let Some(timeout) = timeout {
+        solver.timeout(timeout);
+    }
+    solver
+}
+
+fn solve(
+    file: &Path,
+    kind: SolverKind,
+    timeout: Option<f64>,
+    count: bool,
+) -> Result<u8, Box<dyn Error>> {
+    let (problem, _) = read_puzzle(file)?;
+    problem.validate()?;
+    let timeout = timeout_duration(timeout)?;
+    match kind {
+        SolverKind::Backtrack => run_solve::<SolverBacktrack>(&problem, timeout, count),
+        SolverKind::Inference => run_solve::<SolverBacktrackInference>(&problem, timeout, count),
+        SolverKind::ByCell => run_solve::<SolverBacktrackByCell>(&problem, timeout, count),
+        SolverKind::Propagation => run_solve::<SolverLinePropagation>(&problem, timeout, count),
+    }
+}
+
+fn run_solve<S: Solver>(
+    problem: &Problem,
+    timeout: Option<Duration>,
+    count: bool,
+) -> Result<u8, Box<dyn Error>> {
+    let mut solver = new_solver::<S>(problem, timeout);
+    if count {
+        let cnt = solver.solution_cnt();
+        if !cnt.is_exact {
+            println!("at least {} solutions (timed out)", cnt.count);
+            return Ok(EXIT_TIMEOUT);
+        }
+        println!("{} solutions", cnt.count);
+        return Ok(if cnt.count > 0 { 0 } else { EXIT_NEGATIVE });
+    }
+    match solver.any_solution() {
+        Outcome::Solved(solution) | Outcome::Multiple(solution) => {
+            write_output(None, solution.to_string().as_bytes())?;
+            Ok(0)
+        }
+        Outcome::NoSolution => {
+            println!("no solution");
+            Ok(EXIT_NEGATIVE)
+        }
+        Outcome::TimedOut { .. } | Outcome::Cancelled { .. } => {
+            println!("timed out");
+            Ok(EXIT_TIMEOUT)
+        }
+        Outcome::Invalid(err) => Err(err.into()),
+    }
+}
+
+fn generate(
+    width: usize,
+    height: usize,
+    density: f64,
+    seed: Option<u64>,
+    unique: bool,
+    format: PuzzleFormat,
+    output: Option<PathBuf>,
+) -> Result<u8, Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&density) {
+        return Err("density has to be between 0 and 1".into());
+    }
+    let mut rng = seeded_rng(seed.unwrap_or_else(|| thread_rng().gen()));
+    let problem = match unique {
+        true => random_nonogram_with_unique_solution(width, height, density, &mut rng),
+        false => random_nonogram(width, height, density, &mut rng),
+    };
+    let text = match format {
+        PuzzleFormat::Non => NonPuzzle::from(problem).to_string(),
+        PuzzleFormat::Xml => write_puzzles(&[XmlPuzzle::from(problem)]),
+        PuzzleFormat::Code => format!("{}\n", problem.encode()),
+    };
+    write_output(output, text.as_bytes())?;
+    Ok(0)
+}
+
+fn check(file: &Path, kind: SolverKind, timeout: Option<f64>) -> Result<u8, Box<dyn Error>> {
+    let (problem, _) = read_puzzle(file)?;
+    problem.validate()?;
+    let timeout = timeout_duration(timeout)?;
+    match kind {
+        SolverKind::Backtrack => run_check::<SolverBacktrack>(&problem, timeout),
+        SolverKind::Inference => run_check::<SolverBacktrackInference>(&problem, timeout),
+        SolverKind::ByCell => run_check::<SolverBacktrackByCell>(&problem, timeout),
+        SolverKind::Propagation => run_check::<SolverLinePropagation>(&problem, timeout),
+    }
+}
+
+fn run_check<S: Solver>(
+    problem: &Problem,
+    timeout: Option<Duration>,
+) -> Result<u8, Box<dyn Error>> {
+    match new_solver::<S>(problem, timeout).unique_solution() {
+        Outcome::Solved(_) => {
+            println!("unique solution");
+            Ok(0)
+        }
+        Outcome::Multiple(_) => {
+            println!("multiple solutions");
+            Ok(EXIT_NEGATIVE)
+        }
+        Outcome::NoSolution => {
+            println!("no solution");
+            Ok(EXIT_NEGATIVE)
+        }
+        Outcome::TimedOut { partial_count } | Outcome::Cancelled { partial_count } => {
+            println!("timed out after {} solutions", partial_count);
+            Ok(EXIT_TIMEOUT)
+        }
+        Outcome::Invalid(err) => Err(err.into()),
+    }
+}
+
+fn render(
+    file: &Path,
+    format: ImageFormat,
+    with_solution: bool,
+    cell_size: u32,
+    output: Option<PathBuf>,
+) -> Result<u8, Box<dyn Error>> {
+    let (problem, solution) = read_puzzle(file)?;
+    let solution = match (with_solution, solution) {
+        (false, _) => None,
+        (true, Some(solution)) => Some(solution),
+        (true, None) => Some(
+            SolverLinePropagation::new(&problem)
+                .any_solution()
+                .into_solution()
+                .ok_or("the puzzle has no solution")?,
+        ),
+    };
+    let raster = || {
+        let mut renderer = RasterRenderer::new(&problem);
+        renderer.cell_size(cell_size);
+        if let Some(solution) = &solution {
+            renderer.solution(solution);
+        }
+        renderer.render()
+    };
+    let bytes = match format {
+        ImageFormat::Terminal => match &solution {
+            Some(solution) => solution.to_string().into_bytes(),
+            None => problem.to_string().into_bytes(),
+        },
+        ImageFormat::Svg => {
+            let mut renderer = SvgRenderer::new(&problem);
+            renderer.cell_size(cell_size);
+            if let Some(solution) = &solution {
+                renderer.solution(solution);
+            }
+            renderer.render().into_bytes()
+        }
+        ImageFormat::Pbm => raster().to_pbm(),
+        ImageFormat::Pgm => raster().to_pgm(),
+        #[cfg(feature = "png")]
+        ImageFormat::Png => raster().to_png()?,
+    };
+    write_output(output, &bytes)?;
+    Ok(0)
+}
 
-fn main() {
-    let problem = random_nonogram(5, 5, 0.2);
-    println!("Problem:");
-    println!("{}", problem);
-
-    let mut solver = SolverBacktrackInference::new(&problem);
-    let solution = solver.any_solution();
-    let solution_cnt = solver.solution_cnt();
-    assert!(solution.is_some());
-    let solution = solution.unwrap();
-    assert!(solution.is_correct());
-    println!("Solution:");
-    println!("{}", solution);
-    println!("Solution count: {}", solution_cnt);
-
-    let w = 25;
-    let h = 25;
-    let d = 0.5;
-    let problem = random_nonogram_with_unique_solution(w, h, d);
-    println!("Problem:");
-    println!("{}", problem);
-
-    let mut solver = SolverBacktrackInference::new(&problem);
-    let result = solver.unique_solution();
-    assert!(result.is_unique);
-    let solution = result.solution.unwrap();
-    assert!(solution.is_correct());
-    println!("Solution:");
-    println!("{}", solution);
-
-    let mut filled = 0;
-    for row in &solution.grid {
-        for v in row {
-            filled += *v as usize;
-        }
-    }
-    println!("Expected density: {}", d);
-    println!("Actual density: {}", filled as f64 / (w * h) as f64);
+fn batch(
+    dir: &Path,
+    kind: SolverKind,
+    timeout: Option<f64>,
+    format: ReportFormat,
+    output: Option<PathBuf>,
+) -> Result<u8, Box<dyn Error>> {
+    let mut batch = Batch::new();
+    if let Some(timeout) = timeout_duration(timeout)? {
+        batch.timeout(timeout);
+    }
+    let report = match kind {
+        SolverKind::Backtrack => batch.run::<SolverBacktrack>(dir),
+        SolverKind::Inference => batch.run::<SolverBacktrackInference>(dir),
+        SolverKind::ByCell => batch.run::<SolverBacktrackByCell>(dir),
+        SolverKind::Propagation => batch.run::<SolverLinePropagation>(dir),
+    }
+    .map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let text = match format {
+        ReportFormat::Csv => report.to_csv(),
+        ReportFormat::Json => report.to_json(),
+    };
+    write_output(output, text.as_bytes())?;
+    let summary = report.summary();
+    eprintln!(
+        "{} puzzles: {} unique, {} multiple, {} without solution, {} timed out, {} errors in {:.3}s",
+        summary.puzzles,
+        summary.unique,
+        summary.multiple,
+        summary.no_solution,
+        summary.timed_out,
+        summary.errors,
+        summary.time.as_secs_f64()
+    );
+    Ok(0)
+}
+
+fn booklet(args: BookletArgs) -> Result<u8, Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&args.density) {
+        return Err("density has to be between 0 and 1".into());
+    }
+    let mut booklet = Booklet::new();
+    if let Some(title) = &args.title {
+        booklet.title(title);
+    }
+    if let Some(timeout) = timeout_duration(args.timeout)? {
+        booklet.timeout(timeout);
+    }
+    let mut cnt = 0;
+    for file in &args.files {
+        let puzzles = read_puzzle_file(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+        for puzzle in puzzles {
+            let (problem, solution) = puzzle.map_err(|e| format!("{}: {}", file.display(), e))?;
+            problem
+                .validate()
+                .map_err(|e| format!("{}: {}", file.display(), e))?;
+            match solution {
+                Some(solution) => booklet.solution(&solution),
+                None => booklet.problem(&problem),
+            };
+            cnt += 1;
+        }
+    }
+    let mut rng = seeded_rng(args.seed.unwrap_or_else(|| thread_rng().gen()));
+    for _ in 0..args.generate {
+        let problem =
+            random_nonogram_with_unique_solution(args.width, args.height, args.density, &mut rng);
+        booklet.problem(&problem);
+        cnt += 1;
+    }
+    if cnt == 0 {
+        return Err("no puzzles, pass files or --generate".into());
+    }
+    write_output(args.output, &booklet.render())?;
+    Ok(0)
+}
+
+#[cfg(feature = "tui")]
+fn play(file: &Path) -> Result<u8, Box<dyn Error>> {
+    let (problem, _) = read_puzzle(file)?;
+    problem.validate()?;
+    match nonogram::tui::play(&problem)? {
+        Some(_) => Ok(0),
+        None => Ok(EXIT_NEGATIVE),
+    }
 }
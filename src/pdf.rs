@@ -0,0 +1,357 @@
+// Printable puzzle booklets as PDF 1.4: numbered puzzles laid out in a grid
+// of slots on A4 pages, followed by an answer key with the solutions under the
+// same numbers. Everything is drawn with vector operators and the built-in
+// Helvetica font, so no font or image data is embedded.
+
+use std::time::Duration;
+
+use crate::{
+    layout::Layout,
+    problem::Problem,
+    solver::{Outcome, Solution, Solver},
+    solver_line_propagation::SolverLinePropagation,
+};
+
+// A4 in points
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const PAGE_MARGIN: f64 = 36.0;
+// room for a heading above the slots and page numbers below them
+const HEADER_HEIGHT: f64 = 30.0;
+const FOOTER_HEIGHT: f64 = 20.0;
+const LABEL_SIZE: f64 = 12.0;
+// Helvetica digits are 0.556 em wide
+const DIGIT_WIDTH: f64 = 0.556;
+
+pub struct Booklet {
+    title: Option<String>,
+    puzzles_per_page: usize,
+    answers_per_page: usize,
+    // per puzzle the answer key has to solve
+    timeout: Duration,
+    puzzles: Vec<(Problem, Option<Solution>)>,
+}
+
+impl Default for Booklet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Booklet {
+    pub fn new() -> Self {
+        Self {
+            title: None,
+            puzzles_per_page: 4,
+            answers_per_page: 9,
+            timeout: Duration::from_secs(10),
+            puzzles: vec![],
+        }
+    }
+
+    pub fn title(&mut self, title: &str) -> &mut Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    pub fn puzzles_per_page(&mut self, cnt: usize) -> &mut Self {
+        self.puzzles_per_page = cnt.max(1);
+        self
+    }
+
+    pub fn answers_per_page(&mut self, cnt: usize) -> &mut Self {
+        self.answers_per_page = cnt.max(1);
+        self
+    }
+
+    // Puzzles the answer key cannot solve within it get a note instead.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    // The answer key solves it when rendering.
+    pub fn problem(&mut self, problem: &Problem) -> &mut Self {
+        self.puzzles.push((problem.clone(), None));
+        self
+    }
+
+    pub fn solution(&mut self, solution: &Solution) -> &mut Self {
+        self.puzzles
+            .push((solution.problem.clone(), Some(solution.clone())));
+        self
+    }
+
+    pub fn render(&self) -> Vec<u8> {
+        let mut pages = vec![];
+        for (idx, chunk) in self.puzzles.chunks(self.puzzles_per_page).enumerate() {
+            let heading = match (&self.title, idx) {
+                (Some(title), 0) => title.as_str(),
+                _ => "Puzzles",
+            };
+            let first = idx * self.puzzles_per_page;
+            let slots = chunk
+                .iter()
+                .map(|(problem, _)| (problem.clone(), Answer::Blank))
+                .collect();
+            pages.push(Page {
+                heading,
+                first,
+                per_page: self.puzzles_per_page,
+                answers: false,
+                slots,
+            });
+        }
+        for (idx, chunk) in self.puzzles.chunks(self.answers_per_page).enumerate() {
+            let first = idx * self.answers_per_page;
+            let slots = chunk
+                .iter()
+                .map(|(problem, solution)| {
+                    let answer = match solution {
+                        Some(solution) => Answer::Grid(solution.grid.clone()),
+                        None => match SolverLinePropagation::new(problem)
+                            .timeout(self.timeout)
+                            .unique_solution()
+                        {
+                            Outcome::Solved(solution) => Answer::Grid(solution.grid),
+                            // any one of them would mark the others wrong
+                            Outcome::Multiple(_) => Answer::Note("not unique"),
+                            Outcome::NoSolution => Answer::Note("no solution"),
+                            Outcome::Invalid(_) => Answer::Note("invalid puzzle"),
+                            Outcome::TimedOut { .. } | Outcome::Cancelled { .. } => {
+                                Answer::Note("no solution found")
+                            }
+                        },
+                    };
+                    (problem.clone(), answer)
+                })
+                .collect();
+            pages.push(Page {
+                heading: "Answers",
+                first,
+                per_page: self.answers_per_page,
+                answers: true,
+                slots,
+            });
+        }
+
+        let page_cnt = pages.len();
+        let contents = pages
+            .iter()
+            .enumerate()
+            .map(|(idx, page)| page.content(idx + 1, page_cnt))
+            .collect::<Vec<String>>();
+        write_document(&contents)
+    }
+}
+
+// Maps layout coordinates, measured down from the top left of a puzzle, to
+// page coordinates, measured up from the bottom left of the page.
+struct Transform {
+    left: f64,
+    top: f64,
+    scale: f64,
+}
+
+impl Transform {
+    fn x(&self, x: u32) -> f64 {
+        self.left + x as f64 * self.scale
+    }
+
+    fn y(&self, y: u32) -> f64 {
+        self.top - y as f64 * self.scale
+    }
+}
+
+struct Page<'a> {
+    heading: &'a str,
+    // index of the first puzzle on the page
+    first: usize,
+    per_page: usize,
+    answers: bool,
+    slots: Vec<(Problem, Answer)>,
+}
+
+// What a slot shows besides the puzzle.
+enum Answer {
+    // on the puzzle pages
+    Blank,
+    Grid(Vec<Vec<bool>>),
+    // instead of the puzzle, e.g. if it has no solution
+    Note(&'static str),
+}
+
+impl Page<'_> {
+    fn content(&self, page: usize, page_cnt: usize) -> String {
+        let mut content = String::new();
+        content += &text(
+            PAGE_MARGIN,
+            PAGE_HEIGHT - PAGE_MARGIN - 18.0,
+            18.0,
+            self.heading,
+        );
+        let footer = format!("{} / {}", page, page_cnt);
+        content += &text(
+            PAGE_WIDTH / 2.0 - text_width(&footer, 10.0) / 2.0,
+            PAGE_MARGIN,
+            10.0,
+            &footer,
+        );
+
+        let cols = (self.per_page as f64).sqrt().ceil() as usize;
+        let rows = self.per_page.div_ceil(cols);
+        let slot_width = (PAGE_WIDTH - 2.0 * PAGE_MARGIN) / cols as f64;
+        let slot_height =
+            (PAGE_HEIGHT - 2.0 * PAGE_MARGIN - HEADER_HEIGHT - FOOTER_HEIGHT) / rows as f64;
+        for (idx, (problem, answer)) in self.slots.iter().enumerate() {
+            let slot_left = PAGE_MARGIN + (idx % cols) as f64 * slot_width;
+            let slot_top =
+                PAGE_HEIGHT - PAGE_MARGIN - HEADER_HEIGHT - (idx / cols) as f64 * slot_height;
+            content += &text(
+                slot_left,
+                slot_top - LABEL_SIZE,
+                LABEL_SIZE,
+                &format!("{}.", self.first + idx + 1),
+            );
+            if let Answer::Note(note) = answer {
+                content += &text(slot_left, slot_top - 3.0 * LABEL_SIZE, LABEL_SIZE, note);
+                continue;
+            }
+
+            // the layout in units of 1/10 cell, scaled into the rest of the slot
+            let layout = Layout::new(problem, 10);
+            let (width, height) = (layout.image_width(), layout.image_height());
+            let available_height = slot_height - 1.5 * LABEL_SIZE;
+            let scale = (slot_width / width as f64).min(available_height / height as f64);
+            let transform = Transform {
+                left: slot_left + (slot_width - width as f64 * scale) / 2.0,
+                top: slot_top - 1.5 * LABEL_SIZE,
+                scale,
+            };
+            let cell_size = 10.0 * scale;
+
+            if let Answer::Grid(grid) = answer {
+                for (r, row) in grid.iter().enumerate() {
+                    for (c, _) in row.iter().enumerate().filter(|(_, cell)| **cell) {
+                        let (x, y) = layout.cell(r, c);
+                        content += &format!(
+                            "{:.2} {:.2} {:.2} {:.2} re f\n",
+                            transform.x(x),
+                            transform.y(y) - cell_size,
+                            cell_size,
+                            cell_size
+                        );
+                    }
+                }
+            }
+            // clues only on the puzzle pages, they are noise in the answer key
+            if !self.answers {
+                let font_size = cell_size * 0.6;
+                for label in layout.clue_labels(problem) {
+                    let num = label.num.to_string();
+                    content += &text(
+                        transform.x(label.x) + (cell_size - text_width(&num, font_size)) / 2.0,
+                        transform.y(label.y) - cell_size / 2.0 - font_size * 0.35,
+                        font_size,
+                        &num,
+                    );
+                }
+            }
+            for bold in [false, true] {
+                let line_width = if bold {
+                    cell_size * 0.1
+                } else {
+                    cell_size * 0.03
+                };
+                content += &format!("{:.2} w\n", line_width.max(0.1));
+                for line in layout.grid_lines().iter().filter(|line| line.bold == bold) {
+                    content += &format!(
+                        "{:.2} {:.2} m {:.2} {:.2} l S\n",
+                        transform.x(line.x1),
+                        transform.y(line.y1),
+                        transform.x(line.x2),
+                        transform.y(line.y2)
+                    );
+                }
+            }
+        }
+        content
+    }
+}
+
+fn text_width(text: &str, size: f64) -> f64 {
+    text.chars().count() as f64 * DIGIT_WIDTH * size
+}
+
+fn text(x: f64, y: f64, size: f64, text: &str) -> String {
+    // the built-in fonts only cover ASCII reliably
+    let escaped: String = text
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect::<String>()
+        .replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)");
+    format!(
+        "BT /F1 {:.2} Tf {:.2} {:.2} Td ({}) Tj ET\n",
+        size, x, y, escaped
+    )
+}
+
+// Objects 1 to 3 are the catalog, the page tree and the font, then every page
+// is followed by its content stream.
+fn write_document(contents: &[String]) -> Vec<u8> {
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            (0..contents.len())
+                .map(|idx| format!("{} 0 R", 4 + 2 * idx))
+                .collect::<Vec<_>>()
+                .join(" "),
+            contents.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica >>".to_string(),
+    ];
+    for (idx, content) in contents.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            PAGE_WIDTH,
+            PAGE_HEIGHT,
+            5 + 2 * idx
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (idx, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n{}\nendobj\n", idx + 1, object).bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        pdf.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref
+        )
+        .bytes(),
+    );
+    pdf
+}
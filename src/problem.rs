@@ -1,20 +1,276 @@
-use std::fmt::Display;
+use std::{error::Error, fmt::Display};
 
-use crate::solver::Solution;
+use crate::{csp::VarType, solver::Solution};
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellState {
+    Unknown,
+    Filled,
+    Empty,
+}
+
+impl CellState {
+    // `true` iff a cell in this state may end up `filled`
+    pub fn admits(self, filled: bool) -> bool {
+        match self {
+            CellState::Unknown => true,
+            CellState::Filled => filled,
+            CellState::Empty => !filled,
+        }
+    }
+
+    // Three columns wide, as boards are drawn in the terminal.
+    pub fn glyph(self) -> &'static str {
+        match self {
+            CellState::Unknown => "▔▔▏",
+            CellState::Filled => "▇▇ ",
+            CellState::Empty => "▔╳▏",
+        }
+    }
+}
+
+// `width` and `height` are fixed by the constructors and checked when
+// deserializing. Editing the clue lines can leave them behind, which
+// `validate` reports.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "ProblemData")
+)]
 pub struct Problem {
+    width: usize,
+    height: usize,
+    // one clue line per column and per row, see `validate`
     pub col_info: Vec<Vec<i32>>,
     pub row_info: Vec<Vec<i32>>,
 }
 
+// How a `Problem` is deserialized. Documents from before `width` and
+// `height` were stored leave them out.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ProblemData {
+    #[serde(default)]
+    width: Option<usize>,
+    #[serde(default)]
+    height: Option<usize>,
+    col_info: Vec<Vec<i32>>,
+    row_info: Vec<Vec<i32>>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ProblemData> for Problem {
+    type Error = ProblemError;
+
+    // Only the line counts, invalid clues are still up to `validate`.
+    fn try_from(data: ProblemData) -> Result<Self, Self::Error> {
+        let problem = Problem {
+            width: data.width.unwrap_or(data.col_info.len()),
+            height: data.height.unwrap_or(data.row_info.len()),
+            col_info: data.col_info,
+            row_info: data.row_info,
+        };
+        problem.check_line_counts()?;
+        Ok(problem)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ProblemError {
+    // the number of clue lines differs from `width` or `height`
+    LineCountMismatch {
+        line: VarType,
+        expected: usize,
+        found: usize,
+    },
+    NonPositiveClue {
+        line: VarType,
+        idx: usize,
+        num: i32,
+    },
+    // the blocks with one empty cell between each do not fit into the line
+    ClueTooLong {
+        line: VarType,
+        idx: usize,
+        min_len: u64,
+        len: usize,
+    },
+    // rows and columns fill a different number of cells
+    FilledCountMismatch {
+        rows: u64,
+        cols: u64,
+    },
+    // a `PartialGrid` given to a solver has other dimensions than the problem
+    PartialGridMismatch,
+}
+
+impl Display for ProblemError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemError::LineCountMismatch {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected clues for {} {}s, found {}",
+                expected,
+                line_name(line),
+                found
+            ),
+            ProblemError::NonPositiveClue { line, idx, num } => write!(
+                f,
+                "{} {}: clue number {} is not positive",
+                line_name(line),
+                idx + 1,
+                num
+            ),
+            ProblemError::ClueTooLong {
+                line,
+                idx,
+                min_len,
+                len,
+            } => write!(
+                f,
+                "{} {}: clue needs {} cells, the line has {}",
+                line_name(line),
+                idx + 1,
+                min_len,
+                len
+            ),
+            ProblemError::FilledCountMismatch { rows, cols } => {
+                write!(f, "rows fill {} cells, columns fill {}", rows, cols)
+            }
+            ProblemError::PartialGridMismatch => {
+                write!(f, "the partial grid does not fit the problem")
+            }
+        }
+    }
+}
+
+impl Error for ProblemError {}
+
+fn line_name(line: &VarType) -> &'static str {
+    match line {
+        VarType::Column => "column",
+        VarType::Row => "row",
+    }
+}
+
+impl Problem {
+    // Clues are listed for every line, an empty line may be given as `[]` or
+    // `[0]`.
+    pub fn new(row_info: Vec<Vec<i32>>, col_info: Vec<Vec<i32>>) -> Result<Problem, ProblemError> {
+        let mut problem = Problem::from_clues(row_info, col_info);
+        problem.canonicalize();
+        problem.validate()?;
+        Ok(problem)
+    }
+
+    // Like `new`, one line per row and column, but unchecked.
+    pub fn from_clues(row_info: Vec<Vec<i32>>, col_info: Vec<Vec<i32>>) -> Problem {
+        Problem {
+            width: col_info.len(),
+            height: row_info.len(),
+            col_info,
+            row_info,
+        }
+    }
+
+    // Every line empty.
+    pub fn empty(width: usize, height: usize) -> Problem {
+        Problem {
+            width,
+            height,
+            col_info: vec![vec![]; width],
+            row_info: vec![vec![]; height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Writes empty lines as `[]` instead of `[0]`.
+    pub fn canonicalize(&mut self) {
+        for line in self.col_info.iter_mut().chain(self.row_info.iter_mut()) {
+            if line[..] == [0] {
+                line.clear();
+            }
+        }
+    }
+
+    // Expects canonical clues. Solvers canonicalize their copy and rely on
+    // this holding, they find no solution otherwise.
+    pub fn validate(&self) -> Result<(), ProblemError> {
+        self.check_line_counts()?;
+        let mut filled = vec![];
+        for (line, info, len) in [
+            (VarType::Column, &self.col_info, self.height()),
+            (VarType::Row, &self.row_info, self.width()),
+        ] {
+            let mut total = 0;
+            for (idx, clue) in info.iter().enumerate() {
+                if let Some(num) = clue.iter().find(|num| **num <= 0) {
+                    return Err(ProblemError::NonPositiveClue {
+                        line,
+                        idx,
+                        num: *num,
+                    });
+                }
+                let sum = clue.iter().map(|num| *num as u64).sum::<u64>();
+                let min_len = sum + clue.len().saturating_sub(1) as u64;
+                if min_len > len as u64 {
+                    return Err(ProblemError::ClueTooLong {
+                        line,
+                        idx,
+                        min_len,
+                        len,
+                    });
+                }
+                total += sum;
+            }
+            filled.push(total);
+        }
+        let (cols, rows) = (filled[0], filled[1]);
+        if rows != cols {
+            return Err(ProblemError::FilledCountMismatch { rows, cols });
+        }
+        Ok(())
+    }
+
+    fn check_line_counts(&self) -> Result<(), ProblemError> {
+        for (line, expected, found) in [
+            (VarType::Column, self.width, self.col_info.len()),
+            (VarType::Row, self.height, self.row_info.len()),
+        ] {
+            if expected != found {
+                return Err(ProblemError::LineCountMismatch {
+                    line,
+                    expected,
+                    found,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
 impl From<Vec<Vec<bool>>> for Problem {
     fn from(grid: Vec<Vec<bool>>) -> Self {
+        // without rows there is no telling the width
         if grid.is_empty() {
-            return Self {
-                col_info: vec![],
-                row_info: vec![],
-            };
+            return Problem::empty(0, 0);
         }
         let height = grid.len();
         let width = grid[0].len();
@@ -23,8 +279,8 @@ impl From<Vec<Vec<bool>>> for Problem {
 
         for col in 0..width {
             let mut num = 0;
-            for row in 0..height {
-                if grid[row][col] {
+            for grid_row in &grid {
+                if grid_row[col] {
                     num += 1;
                 } else if num > 0 {
                     col_info[col].push(num);
@@ -35,10 +291,10 @@ impl From<Vec<Vec<bool>>> for Problem {
                 col_info[col].push(num);
             }
         }
-        for row in 0..height {
+        for (row, grid_row) in grid.iter().enumerate() {
             let mut num = 0;
-            for col in 0..width {
-                if grid[row][col] {
+            for &cell in grid_row {
+                if cell {
                     num += 1;
                 } else if num > 0 {
                     row_info[row].push(num);
@@ -50,7 +306,12 @@ impl From<Vec<Vec<bool>>> for Problem {
             }
         }
 
-        Self { col_info, row_info }
+        Self {
+            width,
+            height,
+            col_info,
+            row_info,
+        }
     }
 }
 
@@ -64,3 +325,275 @@ impl Display for Problem {
         Ok(())
     }
 }
+
+// Short URL-safe strings for sharing puzzles. Unpadded base64url of:
+//
+//     version (1 byte, `ENCODING_VERSION`)
+//     kind (1 byte): 0 for clues, 1 for a solution grid
+//     width, height (LEB128 varints)
+//     clues: for every column, then every row, the number of blocks and the
+//            block lengths as varints
+//     grid:  the cells row by row, one bit each, most significant bit first
+pub const ENCODING_VERSION: u8 = 1;
+
+// Larger widths and heights are rejected when decoding, a grid without rows
+// would otherwise allocate for any width without reading a single cell.
+pub const MAX_DIMENSION: usize = 1 << 16;
+
+const KIND_CLUES: u8 = 0;
+const KIND_GRID: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    InvalidCharacter(char),
+    UnsupportedVersion(u8),
+    UnknownKind(u8),
+    // the data ends in the middle of a value
+    Truncated,
+    TrailingData,
+    // a number that does not fit the declared dimensions, or a dimension
+    // above `MAX_DIMENSION`
+    InvalidNumber,
+    // the clues of this line do not fit into its length
+    ClueDoesNotFit(VarType, usize),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::InvalidCharacter(c) => write!(f, "invalid character `{}`", c),
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported encoding version {}", version)
+            }
+            DecodeError::UnknownKind(kind) => write!(f, "unknown encoding kind {}", kind),
+            DecodeError::Truncated => write!(f, "encoded data is truncated"),
+            DecodeError::TrailingData => write!(f, "unexpected data after the puzzle"),
+            DecodeError::InvalidNumber => write!(f, "number out of range"),
+            DecodeError::ClueDoesNotFit(var_type, idx) => {
+                write!(f, "clues of {} {} do not fit", line_name(var_type), idx + 1)
+            }
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl Problem {
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![ENCODING_VERSION, KIND_CLUES];
+        push_varint(&mut bytes, self.width() as u64);
+        push_varint(&mut bytes, self.height() as u64);
+        for line in self.col_info.iter().chain(&self.row_info) {
+            push_varint(&mut bytes, line.len() as u64);
+            for num in line {
+                push_varint(&mut bytes, *num as u64);
+            }
+        }
+        base64url_encode(&bytes)
+    }
+
+    // Accepts both encoded clues and encoded grids.
+    pub fn decode(text: &str) -> Result<Problem, DecodeError> {
+        let bytes = base64url_decode(text)?;
+        let mut reader = Reader::new(&bytes)?;
+        let (width, height) = (reader.dimension()?, reader.dimension()?);
+        let problem = match reader.kind {
+            KIND_CLUES => {
+                // every line takes at least one byte
+                if width
+                    .checked_add(height)
+                    .is_none_or(|lines| lines > reader.remaining())
+                {
+                    return Err(DecodeError::Truncated);
+                }
+                let mut read_lines = |var_type: VarType, cnt: usize, len: usize| {
+                    (0..cnt)
+                        .map(|idx| {
+                            let line = (0..reader.varint()?)
+                                .map(|_| match reader.varint()? {
+                                    num @ 1..=0x7fff_ffff => Ok(num as i32),
+                                    _ => Err(DecodeError::InvalidNumber),
+                                })
+                                .collect::<Result<Vec<i32>, DecodeError>>()?;
+                            let min_len = line.iter().map(|num| *num as u64 + 1).sum::<u64>();
+                            if min_len > len as u64 + 1 {
+                                return Err(DecodeError::ClueDoesNotFit(var_type.clone(), idx));
+                            }
+                            Ok(line)
+                        })
+                        .collect::<Result<Vec<Vec<i32>>, DecodeError>>()
+                };
+                let col_info = read_lines(VarType::Column, width, height)?;
+                let row_info = read_lines(VarType::Row, height, width)?;
+                Problem {
+                    width,
+                    height,
+                    col_info,
+                    row_info,
+                }
+            }
+            _ => {
+                let grid = reader.grid(width, height)?;
+                match height {
+                    0 => Problem::empty(width, 0),
+                    _ => Problem::from(grid),
+                }
+            }
+        };
+        reader.finish()?;
+        Ok(problem)
+    }
+}
+
+pub fn encode_grid(grid: &[Vec<bool>]) -> String {
+    let width = grid.first().map_or(0, |row| row.len());
+    let mut bytes = vec![ENCODING_VERSION, KIND_GRID];
+    push_varint(&mut bytes, width as u64);
+    push_varint(&mut bytes, grid.len() as u64);
+    let mut bits = vec![0u8; (width * grid.len()).div_ceil(8)];
+    for (idx, cell) in grid.iter().flatten().enumerate() {
+        if *cell {
+            bits[idx / 8] |= 0x80 >> (idx % 8);
+        }
+    }
+    bytes.extend(bits);
+    base64url_encode(&bytes)
+}
+
+pub fn decode_grid(text: &str) -> Result<Vec<Vec<bool>>, DecodeError> {
+    let bytes = base64url_decode(text)?;
+    let mut reader = Reader::new(&bytes)?;
+    if reader.kind != KIND_GRID {
+        return Err(DecodeError::UnknownKind(reader.kind));
+    }
+    let (width, height) = (reader.dimension()?, reader.dimension()?);
+    let grid = reader.grid(width, height)?;
+    reader.finish()?;
+    Ok(grid)
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    kind: u8,
+}
+
+impl<'a> Reader<'a> {
+    // Checks the header.
+    fn new(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        match bytes {
+            [] | [_] => Err(DecodeError::Truncated),
+            [ENCODING_VERSION, kind, ..] if *kind == KIND_CLUES || *kind == KIND_GRID => Ok(Self {
+                bytes,
+                pos: 2,
+                kind: *kind,
+            }),
+            [ENCODING_VERSION, kind, ..] => Err(DecodeError::UnknownKind(*kind)),
+            [version, ..] => Err(DecodeError::UnsupportedVersion(*version)),
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.bytes.get(self.pos).ok_or(DecodeError::Truncated)?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64)
+                .checked_shl(shift)
+                .ok_or(DecodeError::InvalidNumber)?;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(DecodeError::InvalidNumber)
+    }
+
+    fn dimension(&mut self) -> Result<usize, DecodeError> {
+        match usize::try_from(self.varint()?) {
+            Ok(dimension) if dimension <= MAX_DIMENSION => Ok(dimension),
+            _ => Err(DecodeError::InvalidNumber),
+        }
+    }
+
+    fn grid(&mut self, width: usize, height: usize) -> Result<Vec<Vec<bool>>, DecodeError> {
+        let cells = width
+            .checked_mul(height)
+            .ok_or(DecodeError::InvalidNumber)?;
+        if cells.div_ceil(8) > self.remaining() {
+            return Err(DecodeError::Truncated);
+        }
+        let bits = &self.bytes[self.pos..self.pos + cells.div_ceil(8)];
+        self.pos += bits.len();
+        Ok((0..height)
+            .map(|r| {
+                (0..width)
+                    .map(|c| {
+                        let idx = r * width + c;
+                        bits[idx / 8] & (0x80 >> (idx % 8)) != 0
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn finish(&self) -> Result<(), DecodeError> {
+        if self.remaining() > 0 {
+            return Err(DecodeError::TrailingData);
+        }
+        Ok(())
+    }
+}
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+fn base64url_encode(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (idx, byte)| {
+            group | (*byte as u32) << (16 - 8 * idx)
+        });
+        for idx in 0..=chunk.len() {
+            text.push(BASE64URL[(group >> (18 - 6 * idx) & 0x3f) as usize] as char);
+        }
+    }
+    text
+}
+
+fn base64url_decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let values = text
+        .chars()
+        .map(|c| {
+            BASE64URL
+                .iter()
+                .position(|b| *b as char == c)
+                .map(|value| value as u32)
+                .ok_or(DecodeError::InvalidCharacter(c))
+        })
+        .collect::<Result<Vec<u32>, DecodeError>>()?;
+    let mut bytes = vec![];
+    for chunk in values.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(DecodeError::Truncated);
+        }
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (idx, value)| group | value << (18 - 6 * idx));
+        for idx in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * idx)) as u8);
+        }
+    }
+    Ok(bytes)
+}
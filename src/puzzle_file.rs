@@ -0,0 +1,99 @@
+// Reading puzzles from files in any of the supported formats. The format
+// follows the file extension: `.xml` and `.pbn` are XML puzzle sets, `.pbm`,
+// `.pgm`, `.pnm` and `.png` are images whose dark pixels are the solution, and
+// everything else is read as `.non`.
+
+use std::{error::Error, fmt::Display, fs, io, path::Path};
+
+use crate::{
+    format_non::{NonError, NonPuzzle},
+    format_xml::{read_puzzles, XmlError},
+    import::ImageImporter,
+    problem::Problem,
+    raster::{Image, ImageError},
+    solver::Solution,
+};
+
+// Extensions of the files `is_puzzle_file` accepts.
+pub const PUZZLE_EXTENSIONS: [&str; 7] = ["non", "xml", "pbn", "pbm", "pgm", "pnm", "png"];
+
+#[derive(Debug)]
+pub enum PuzzleFileError {
+    Io(io::Error),
+    NotUtf8,
+    Non(NonError),
+    Xml(XmlError),
+    Image(ImageError),
+}
+
+impl Display for PuzzleFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleFileError::Io(e) => write!(f, "{}", e),
+            PuzzleFileError::NotUtf8 => write!(f, "not a UTF-8 text file"),
+            PuzzleFileError::Non(e) => write!(f, "{}", e),
+            PuzzleFileError::Xml(e) => write!(f, "{}", e),
+            PuzzleFileError::Image(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for PuzzleFileError {}
+
+impl From<io::Error> for PuzzleFileError {
+    fn from(e: io::Error) -> Self {
+        PuzzleFileError::Io(e)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase()
+}
+
+pub fn is_puzzle_file(path: &Path) -> bool {
+    PUZZLE_EXTENSIONS.contains(&extension(path).as_str())
+}
+
+// A puzzle with the solution its file comes with, if any.
+pub type FilePuzzle = (Problem, Option<Solution>);
+
+// Every puzzle in the file with its solution if the file has one. Only XML
+// files can hold more than one puzzle, or none at all, and fail per puzzle
+// when some of them are not supported.
+pub fn read_puzzle_file(
+    path: &Path,
+) -> Result<Vec<Result<FilePuzzle, PuzzleFileError>>, PuzzleFileError> {
+    let bytes = fs::read(path)?;
+    let text = || String::from_utf8(bytes.clone()).map_err(|_| PuzzleFileError::NotUtf8);
+    match extension(path).as_str() {
+        "xml" | "pbn" => Ok(read_puzzles(&text()?)
+            .map_err(PuzzleFileError::Xml)?
+            .into_iter()
+            .map(|puzzle| {
+                puzzle
+                    .map(|puzzle| (puzzle.problem, puzzle.solution))
+                    .map_err(PuzzleFileError::Xml)
+            })
+            .collect()),
+        "pbm" | "pgm" | "pnm" | "png" => {
+            let image = Image::decode(&bytes).map_err(PuzzleFileError::Image)?;
+            let grid = ImageImporter::new().grid(&image);
+            let problem = match grid.is_empty() {
+                true => Problem::empty(image.width as usize, 0),
+                false => Problem::from(grid.clone()),
+            };
+            let solution = Solution {
+                problem: problem.clone(),
+                grid,
+            };
+            Ok(vec![Ok((problem, Some(solution)))])
+        }
+        _ => {
+            let puzzle: NonPuzzle = text()?.parse().map_err(PuzzleFileError::Non)?;
+            Ok(vec![Ok((puzzle.problem, puzzle.goal))])
+        }
+    }
+}
@@ -0,0 +1,264 @@
+// Grayscale images and the netpbm formats: PBM (`P4`, one bit per pixel) and
+// PGM (`P5`, one byte per pixel). Their plain text variants `P1` and `P2` can
+// be read as well. PNG needs the `png` feature.
+
+use std::{error::Error, fmt::Display};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageError {
+    UnsupportedFormat,
+    InvalidHeader,
+    InvalidPixel,
+    // fewer pixels than the header announces
+    Truncated,
+    // decoder message
+    Png(String),
+}
+
+impl Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::UnsupportedFormat => write!(f, "not a PBM, PGM or PNG image"),
+            ImageError::InvalidHeader => write!(f, "invalid image header"),
+            ImageError::InvalidPixel => write!(f, "invalid pixel value"),
+            ImageError::Truncated => write!(f, "image data is truncated"),
+            ImageError::Png(message) => write!(f, "invalid PNG: {}", message),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    // row by row, 0 is black and 255 white
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32, value: u8) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![value; width as usize * height as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        self.pixels[y as usize * self.width as usize + x as usize]
+    }
+
+    // Pixels outside the image are ignored.
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, value: u8) {
+        let right = (x + width).min(self.width);
+        let bottom = (y + height).min(self.height);
+        for row in y.min(bottom)..bottom {
+            let start = row as usize * self.width as usize;
+            self.pixels[start + x.min(right) as usize..start + right as usize].fill(value);
+        }
+    }
+
+    // Detects the format from the first bytes.
+    pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+        match bytes {
+            [b'P', b'1'..=b'5', ..] => Self::from_pnm(bytes),
+            #[cfg(feature = "png")]
+            [0x89, b'P', b'N', b'G', ..] => Self::from_png(bytes),
+            _ => Err(ImageError::UnsupportedFormat),
+        }
+    }
+
+    // PBM or PGM, binary or plain. Gray values are scaled to 0..=255.
+    pub fn from_pnm(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut pos = 2;
+        let magic = match bytes {
+            [b'P', magic @ b'1'..=b'5', ..] => *magic,
+            _ => return Err(ImageError::UnsupportedFormat),
+        };
+        if magic == b'3' {
+            return Err(ImageError::UnsupportedFormat);
+        }
+        let bitmap = magic == b'1' || magic == b'4';
+        let width = next_number(bytes, &mut pos).ok_or(ImageError::InvalidHeader)?;
+        let height = next_number(bytes, &mut pos).ok_or(ImageError::InvalidHeader)?;
+        let max_value = match bitmap {
+            true => 1,
+            false => match next_number(bytes, &mut pos) {
+                Some(value @ 1..=65535) => value,
+                _ => return Err(ImageError::InvalidHeader),
+            },
+        };
+        let (Ok(width), Ok(height)) = (u32::try_from(width), u32::try_from(height)) else {
+            return Err(ImageError::InvalidHeader);
+        };
+        let cnt = width as usize * height as usize;
+        // every pixel takes at least a bit
+        if cnt / 8 > bytes.len() {
+            return Err(ImageError::Truncated);
+        }
+        let scale = |value: u64| (value * 255 / max_value) as u8;
+
+        let pixels = match magic {
+            b'1' => {
+                let mut pixels = Vec::with_capacity(cnt);
+                while pixels.len() < cnt {
+                    skip_whitespace(bytes, &mut pos);
+                    match bytes.get(pos) {
+                        Some(b'0') => pixels.push(255),
+                        Some(b'1') => pixels.push(0),
+                        Some(_) => return Err(ImageError::InvalidPixel),
+                        None => return Err(ImageError::Truncated),
+                    }
+                    pos += 1;
+                }
+                pixels
+            }
+            b'2' => (0..cnt)
+                .map(|_| match next_number(bytes, &mut pos) {
+                    Some(value) if value <= max_value => Ok(scale(value)),
+                    Some(_) => Err(ImageError::InvalidPixel),
+                    None => Err(ImageError::Truncated),
+                })
+                .collect::<Result<Vec<u8>, ImageError>>()?,
+            _ => {
+                // a single whitespace byte ends the header
+                pos += 1;
+                let data = bytes.get(pos..).ok_or(ImageError::Truncated)?;
+                if magic == b'4' {
+                    let row_len = (width as usize).div_ceil(8);
+                    if data.len() < row_len * height as usize {
+                        return Err(ImageError::Truncated);
+                    }
+                    (0..cnt)
+                        .map(|idx| {
+                            let (y, x) = (idx / width as usize, idx % width as usize);
+                            match data[y * row_len + x / 8] & (0x80 >> (x % 8)) {
+                                0 => 255,
+                                _ => 0,
+                            }
+                        })
+                        .collect()
+                } else {
+                    let depth = if max_value > 255 { 2 } else { 1 };
+                    if data.len() < cnt * depth {
+                        return Err(ImageError::Truncated);
+                    }
+                    data[..cnt * depth]
+                        .chunks(depth)
+                        .map(|value| {
+                            let value = value.iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+                            match value <= max_value {
+                                true => Ok(scale(value)),
+                                false => Err(ImageError::InvalidPixel),
+                            }
+                        })
+                        .collect::<Result<Vec<u8>, ImageError>>()?
+                }
+            }
+        };
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    // Colors are converted to their luma, transparent pixels count as white.
+    #[cfg(feature = "png")]
+    pub fn from_png(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder
+            .read_info()
+            .map_err(|e| ImageError::Png(e.to_string()))?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buffer)
+            .map_err(|e| ImageError::Png(e.to_string()))?;
+        let channels = info.color_type.samples();
+        let pixels = buffer[..info.buffer_size()]
+            .chunks(channels)
+            .map(|pixel| {
+                let (color, alpha) = match pixel {
+                    [gray] => (*gray as u32, 255),
+                    [gray, alpha] => (*gray as u32, *alpha as u32),
+                    [r, g, b] => (luma(*r, *g, *b), 255),
+                    [r, g, b, alpha, ..] => (luma(*r, *g, *b), *alpha as u32),
+                    [] => (255, 255),
+                };
+                // blended over white
+                ((color * alpha + 255 * (255 - alpha)) / 255) as u8
+            })
+            .collect();
+        Ok(Image {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    // Pixels darker than middle gray are black.
+    pub fn to_pbm(&self) -> Vec<u8> {
+        let mut bytes = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        let row_len = (self.width as usize).div_ceil(8);
+        for y in 0..self.height {
+            let mut row = vec![0u8; row_len];
+            for x in 0..self.width {
+                if self.get(x, y) < 128 {
+                    row[x as usize / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            bytes.extend(row);
+        }
+        bytes
+    }
+
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let mut bytes = format!("P5\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend(&self.pixels);
+        bytes
+    }
+
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = vec![];
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(feature = "png")]
+fn luma(r: u8, g: u8, b: u8) -> u32 {
+    (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000
+}
+
+// Whitespace and `#` comments up to the end of their line.
+fn skip_whitespace(bytes: &[u8], pos: &mut usize) {
+    while let Some(byte) = bytes.get(*pos) {
+        match byte {
+            b'#' => {
+                while bytes.get(*pos).is_some_and(|b| *b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            _ if byte.is_ascii_whitespace() => *pos += 1,
+            _ => return,
+        }
+    }
+}
+
+fn next_number(bytes: &[u8], pos: &mut usize) -> Option<u64> {
+    skip_whitespace(bytes, pos);
+    let start = *pos;
+    while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+        *pos += 1;
+    }
+    std::str::from_utf8(&bytes[start..*pos]).ok()?.parse().ok()
+}
@@ -0,0 +1,107 @@
+use crate::{layout::Layout, problem::Problem, raster::Image, solver::Solution};
+
+const BLACK: u8 = 0;
+const WHITE: u8 = 255;
+
+// 3x5 digit glyphs, one row per entry, most significant of the 3 bits on the
+// left.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+pub struct RasterRenderer {
+    problem: Problem,
+    cell_size: u32,
+    // filled cells drawn over the empty grid
+    solution: Option<Vec<Vec<bool>>>,
+}
+
+impl RasterRenderer {
+    pub fn new(problem: &Problem) -> Self {
+        Self {
+            problem: problem.clone(),
+            cell_size: 12,
+            solution: None,
+        }
+    }
+
+    // At least 1, digits get readable from about 8 pixels on.
+    pub fn cell_size(&mut self, cell_size: u32) -> &mut Self {
+        self.cell_size = cell_size.max(1);
+        self
+    }
+
+    pub fn solution(&mut self, solution: &Solution) -> &mut Self {
+        self.solution = Some(solution.grid.clone());
+        self
+    }
+
+    pub fn render(&self) -> Image {
+        let layout = Layout::new(&self.problem, self.cell_size);
+        let mut image = Image::new(layout.image_width(), layout.image_height(), WHITE);
+
+        if let Some(grid) = &self.solution {
+            for (r, row) in grid.iter().enumerate() {
+                for (c, _) in row.iter().enumerate().filter(|(_, cell)| **cell) {
+                    let (x, y) = layout.cell(r, c);
+                    image.fill_rect(x, y, self.cell_size, self.cell_size, BLACK);
+                }
+            }
+        }
+
+        for label in layout.clue_labels(&self.problem) {
+            draw_number(&mut image, label.x, label.y, self.cell_size, label.num);
+        }
+
+        for line in layout.grid_lines() {
+            // bold lines grow to the top left
+            let offset = if line.bold { 1 } else { 0 };
+            let x = line.x1.saturating_sub(offset);
+            let y = line.y1.saturating_sub(offset);
+            image.fill_rect(x, y, line.x2 - x + 1, line.y2 - y + 1, BLACK);
+        }
+        image
+    }
+}
+
+// Centered in the `cell_size` square at `x`, `y`, as large as fits.
+fn draw_number(image: &mut Image, x: u32, y: u32, cell_size: u32, num: i32) {
+    let digits: Vec<usize> = num
+        .to_string()
+        .bytes()
+        .filter(u8::is_ascii_digit)
+        .map(|b| (b - b'0') as usize)
+        .collect();
+    let cnt = digits.len() as u32;
+    // glyphs are 3 units wide with 1 unit spacing, 5 units high, and keep
+    // 1 unit of padding around them
+    let scale = (cell_size / (4 * cnt + 1)).min(cell_size / 7).max(1);
+    let text_width = (4 * cnt - 1) * scale;
+    let left = (x + cell_size / 2).saturating_sub(text_width / 2);
+    let top = (y + cell_size / 2).saturating_sub(5 * scale / 2);
+    for (idx, digit) in digits.into_iter().enumerate() {
+        let glyph_left = left + idx as u32 * 4 * scale;
+        for (row, bits) in DIGITS[digit].iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) != 0 {
+                    image.fill_rect(
+                        glyph_left + col * scale,
+                        top + row as u32 * scale,
+                        scale,
+                        scale,
+                        BLACK,
+                    );
+                }
+            }
+        }
+    }
+}
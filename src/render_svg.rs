@@ -0,0 +1,86 @@
+use crate::{layout::Layout, problem::Problem, solver::Solution};
+
+pub struct SvgRenderer {
+    problem: Problem,
+    cell_size: u32,
+    // filled cells drawn over the empty grid
+    solution: Option<Vec<Vec<bool>>>,
+}
+
+impl SvgRenderer {
+    pub fn new(problem: &Problem) -> Self {
+        Self {
+            problem: problem.clone(),
+            cell_size: 20,
+            solution: None,
+        }
+    }
+
+    // At least 1, like `RasterRenderer::cell_size`.
+    pub fn cell_size(&mut self, cell_size: u32) -> &mut Self {
+        self.cell_size = cell_size.max(1);
+        self
+    }
+
+    pub fn solution(&mut self, solution: &Solution) -> &mut Self {
+        self.solution = Some(solution.grid.clone());
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let layout = Layout::new(&self.problem, self.cell_size);
+        let (width, height) = (layout.image_width(), layout.image_height());
+        let mut svg = String::new();
+        svg += &format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+            width, height, width, height
+        );
+        svg += &format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"white\"/>\n",
+            width, height
+        );
+
+        if let Some(grid) = &self.solution {
+            svg += "<g fill=\"black\">\n";
+            for (r, row) in grid.iter().enumerate() {
+                for (c, _) in row.iter().enumerate().filter(|(_, cell)| **cell) {
+                    let (x, y) = layout.cell(r, c);
+                    svg += &format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                        x, y, self.cell_size, self.cell_size
+                    );
+                }
+            }
+            svg += "</g>\n";
+        }
+
+        svg += &format!(
+            "<g font-family=\"sans-serif\" font-size=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">\n",
+            self.cell_size * 3 / 5
+        );
+        for label in layout.clue_labels(&self.problem) {
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\">{}</text>\n",
+                label.x as f64 + self.cell_size as f64 / 2.0,
+                label.y as f64 + self.cell_size as f64 / 2.0,
+                label.num
+            );
+        }
+        svg += "</g>\n";
+
+        svg += "<g stroke=\"black\" stroke-linecap=\"square\">\n";
+        for line in layout.grid_lines() {
+            svg += &format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke-width=\"{}\"/>\n",
+                line.x1,
+                line.y1,
+                line.x2,
+                line.y2,
+                if line.bold { 2 } else { 1 }
+            );
+        }
+        svg += "</g>\n";
+        svg += "</svg>\n";
+        svg
+    }
+}
@@ -1,8 +1,20 @@
-use std::{fmt::Display, time::Duration};
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use crate::problem::Problem;
+use crate::{
+    csp::VarType,
+    line_solver::solve_line,
+    problem::{CellState, Problem, ProblemError},
+};
 
-#[derive(Clone)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Solution {
     pub problem: Problem,
     pub grid: Vec<Vec<bool>>,
@@ -10,54 +22,293 @@ pub struct Solution {
 
 impl Solution {
     pub fn is_correct(&self) -> bool {
-        let problem = Problem::from(self.grid.clone());
-        problem.col_info == self.problem.col_info && problem.row_info == self.problem.row_info
+        let (width, height) = (self.problem.width(), self.problem.height());
+        if self.grid.len() != height || self.grid.iter().any(|row| row.len() != width) {
+            return false;
+        }
+        let mut expected = self.problem.clone();
+        expected.canonicalize();
+        match height {
+            0 => expected == Problem::empty(width, 0),
+            _ => expected == Problem::from(self.grid.clone()),
+        }
     }
 }
 
 impl Display for Solution {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (r, row) in self.grid.iter().enumerate() {
-            write!(f, " ")?;
-            for cell in row {
-                write!(f, "{}", if *cell { "▇▇ " } else { "▔▔▏" })?;
+        write_grid(f, &self.problem, |r, c| {
+            if self.grid[r][c] {
+                "▇▇ "
+            } else {
+                "▔▔▏"
             }
-            for num in &self.problem.row_info[r] {
-                write!(f, "{:3}", num)?;
-            }
-            writeln!(f)?;
+        })
+    }
+}
+
+// A board where some cells may not be decided yet.
+#[derive(Clone)]
+pub struct PartialGrid {
+    pub problem: Problem,
+    pub grid: Vec<Vec<CellState>>,
+}
+
+impl PartialGrid {
+    pub fn new(problem: &Problem) -> Self {
+        Self {
+            problem: problem.clone(),
+            grid: vec![vec![CellState::Unknown; problem.width()]; problem.height()],
+        }
+    }
+
+    pub fn fits(&self, problem: &Problem) -> bool {
+        self.grid.len() == problem.height()
+            && self.grid.iter().all(|row| row.len() == problem.width())
+    }
+
+    pub fn row(&self, row: usize) -> Vec<CellState> {
+        self.grid[row].clone()
+    }
+
+    pub fn col(&self, col: usize) -> Vec<CellState> {
+        self.grid.iter().map(|row| row[col]).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.grid
+            .iter()
+            .all(|row| row.iter().all(|cell| *cell != CellState::Unknown))
+    }
+
+    // `false` iff the board does not fit its problem or some row or column
+    // can no longer be completed according to its clue.
+    pub fn is_consistent(&self) -> bool {
+        self.fits(&self.problem) && self.conflicting_lines().is_empty()
+    }
+
+    // Rows and columns whose decided cells contradict their clue.
+    pub fn conflicting_lines(&self) -> Vec<(VarType, usize)> {
+        let rows = (0..self.grid.len())
+            .filter(|row| solve_line(&self.problem.row_info[*row], &self.row(*row)).is_none())
+            .map(|row| (VarType::Row, row));
+        let cols = (0..self.problem.width())
+            .filter(|col| solve_line(&self.problem.col_info[*col], &self.col(*col)).is_none())
+            .map(|col| (VarType::Column, col));
+        rows.chain(cols).collect()
+    }
+
+    // `None` if some cell is still unknown.
+    pub fn to_solution(&self) -> Option<Solution> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(Solution {
+            problem: self.problem.clone(),
+            grid: self
+                .grid
+                .iter()
+                .map(|row| row.iter().map(|cell| *cell == CellState::Filled).collect())
+                .collect(),
+        })
+    }
+}
+
+impl From<Solution> for PartialGrid {
+    fn from(solution: Solution) -> Self {
+        Self {
+            grid: solution
+                .grid
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| {
+                            if *cell {
+                                CellState::Filled
+                            } else {
+                                CellState::Empty
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+            problem: solution.problem,
+        }
+    }
+}
+
+impl Display for PartialGrid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_grid(f, &self.problem, |r, c| self.grid[r][c].glyph())
+    }
+}
+
+fn write_grid<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    problem: &Problem,
+    cell: impl Fn(usize, usize) -> &'a str,
+) -> std::fmt::Result {
+    // the clue lines rather than the dimensions, they may have been edited
+    let width = problem.col_info.len();
+    for (r, row_info) in problem.row_info.iter().enumerate() {
+        write!(f, " ")?;
+        for c in 0..width {
+            write!(f, "{}", cell(r, c))?;
+        }
+        for num in row_info {
+            write!(f, "{:3}", num)?;
         }
         writeln!(f)?;
-        let mut i = 0;
-        loop {
-            let mut finish = true;
-            for col in 0..self.grid[0].len() {
-                if self.problem.col_info[col].len() > i {
-                    finish = false;
-                    write!(f, "{:3}", self.problem.col_info[col][i])?;
-                } else {
-                    write!(f, "   ")?;
-                }
-            }
-            writeln!(f)?;
-            if finish {
-                break;
+    }
+    writeln!(f)?;
+    let mut i = 0;
+    loop {
+        let mut finish = true;
+        for col_info in &problem.col_info {
+            if col_info.len() > i {
+                finish = false;
+                write!(f, "{:3}", col_info[i])?;
+            } else {
+                write!(f, "   ")?;
             }
-            i += 1;
         }
-        Ok(())
+        writeln!(f)?;
+        if finish {
+            break;
+        }
+        i += 1;
     }
+    Ok(())
 }
 
 pub trait Solver {
     fn new(problem: &Problem) -> Self;
     fn timeout(&mut self, duration: Duration) -> &mut Self;
-    fn any_solution(&mut self) -> Option<Solution>;
-    fn unique_solution(&mut self) -> UniqueSolutionResult;
-    fn solution_cnt(&mut self) -> u32;
+    // The search stops soon after `token` is cancelled, like on a timeout.
+    fn cancel_token(&mut self, token: &CancelToken) -> &mut Self;
+    // Decided cells of `grid` are kept in every solution. There is no
+    // solution if they contradict the clues.
+    fn partial_grid(&mut self, grid: &PartialGrid) -> &mut Self;
+    // Stops at the first solution, never `Multiple`. `Invalid` if the problem
+    // does not validate or the partial grid does not fit it.
+    fn any_solution(&mut self) -> Outcome;
+    // Stops at the second solution.
+    fn unique_solution(&mut self) -> Outcome;
+    // An exact 0 for invalid input, `unique_solution` tells the reason.
+    fn solution_cnt(&mut self) -> SolutionCount;
+}
+
+// What a search found out.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Outcome {
+    // the only solution, or just the first one for `any_solution`
+    Solved(Solution),
+    NoSolution,
+    // one of at least two solutions
+    Multiple(Solution),
+    // the timeout expired after finding `partial_count` solutions, not
+    // enough to tell the outcome
+    TimedOut { partial_count: u32 },
+    // like `TimedOut`, but stopped by a `CancelToken`
+    Cancelled { partial_count: u32 },
+    // nothing was searched, the input is broken
+    Invalid(ProblemError),
 }
 
-pub struct UniqueSolutionResult {
-    pub solution: Option<Solution>,
-    pub is_unique: bool
-}
\ No newline at end of file
+impl Outcome {
+    // For solvers: the first solution found, how many were found and whether
+    // the search was cut short.
+    pub fn from_search(
+        solution: Option<Solution>,
+        solution_cnt: u32,
+        timed_out: bool,
+        cancelled: bool,
+    ) -> Self {
+        match solution {
+            Some(solution) if solution_cnt >= 2 => Outcome::Multiple(solution),
+            _ if cancelled => Outcome::Cancelled {
+                partial_count: solution_cnt,
+            },
+            _ if timed_out => Outcome::TimedOut {
+                partial_count: solution_cnt,
+            },
+            Some(solution) => Outcome::Solved(solution),
+            None => Outcome::NoSolution,
+        }
+    }
+
+    pub fn solution(&self) -> Option<&Solution> {
+        match self {
+            Outcome::Solved(solution) | Outcome::Multiple(solution) => Some(solution),
+            Outcome::NoSolution
+            | Outcome::TimedOut { .. }
+            | Outcome::Cancelled { .. }
+            | Outcome::Invalid(_) => None,
+        }
+    }
+
+    pub fn into_solution(self) -> Option<Solution> {
+        match self {
+            Outcome::Solved(solution) | Outcome::Multiple(solution) => Some(solution),
+            Outcome::NoSolution
+            | Outcome::TimedOut { .. }
+            | Outcome::Cancelled { .. }
+            | Outcome::Invalid(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolutionCount {
+    pub count: u32,
+    // `false` if the search timed out or was cancelled, `count` is a lower
+    // bound then
+    pub is_exact: bool,
+}
+
+// Stops solvers from another thread, e.g. when nobody waits for the result
+// anymore. Clones share the flag, and a cancelled token stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    // `is_cancelled` calls left before the token cancels itself
+    checks_left: Option<Arc<AtomicU64>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Cancels itself after `checks` calls of `is_cancelled`. Solvers check
+    // once per search node, so this stops after the same work on every run,
+    // unlike a timeout.
+    pub fn after_checks(checks: u64) -> Self {
+        Self {
+            cancelled: Arc::default(),
+            checks_left: Some(Arc::new(AtomicU64::new(checks))),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        if let Some(checks_left) = &self.checks_left {
+            let counted = checks_left.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            });
+            if counted.is_err() {
+                self.cancel();
+            }
+        }
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
@@ -1,9 +1,9 @@
-use std::{time::{Duration, Instant}, u64};
+use std::time::{Duration, Instant};
 
 use crate::{
     csp::{enumerate_domain, Domain, Line, VarType},
-    problem::Problem,
-    solver::{Solution, Solver, UniqueSolutionResult},
+    problem::{Problem, ProblemError},
+    solver::{CancelToken, Outcome, PartialGrid, Solution, SolutionCount, Solver},
 };
 
 pub struct SolverBacktrack {
@@ -16,14 +16,20 @@ pub struct SolverBacktrack {
     row_assignments: Vec<Option<Line>>,
     solution_cnt: u32,
     solution: Option<Solution>,
+    given: Option<PartialGrid>,
     timeout: Duration,
     start: Instant,
+    timed_out: bool,
+    cancel: CancelToken,
+    cancelled: bool,
 }
 
 impl Solver for SolverBacktrack {
     fn new(problem: &Problem) -> Self {
+        let mut problem = problem.clone();
+        problem.canonicalize();
         Self {
-            problem: problem.clone(),
+            problem,
             width: 0,
             height: 0,
             col_domains: vec![],
@@ -32,8 +38,12 @@ impl Solver for SolverBacktrack {
             row_assignments: vec![],
             solution_cnt: 0,
             solution: None,
+            given: None,
             timeout: Duration::from_secs(u64::MAX),
             start: Instant::now(),
+            timed_out: false,
+            cancel: CancelToken::new(),
+            cancelled: false,
         }
     }
 
@@ -42,34 +52,58 @@ impl Solver for SolverBacktrack {
         self
     }
 
-    fn any_solution(&mut self) -> Option<Solution> {
-        self.init();
-        if self.search(1) {
-            return self.solution.clone();
+    fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    fn partial_grid(&mut self, grid: &PartialGrid) -> &mut Self {
+        self.given = Some(grid.clone());
+        self
+    }
+
+    fn any_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
-        None
+        self.search(1);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn unique_solution(&mut self) -> UniqueSolutionResult {
-        self.init();
-        self.search(2);
-        UniqueSolutionResult {
-            solution: self.solution.clone(),
-            is_unique: self.solution_cnt == 1,
+    fn unique_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
+        self.search(2);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn solution_cnt(&mut self) -> u32 {
-        self.init();
-        self.search(u32::MAX);
-        self.solution_cnt
+    fn solution_cnt(&mut self) -> SolutionCount {
+        if self.init().is_ok() {
+            self.search(u32::MAX);
+        }
+        SolutionCount {
+            count: self.solution_cnt,
+            is_exact: !self.timed_out && !self.cancelled,
+        }
     }
 }
 
 impl SolverBacktrack {
-    fn init(&mut self) {
-        self.width = self.problem.col_info.len();
-        self.height = self.problem.row_info.len();
+    // `Err` iff the problem is invalid or the given cells do not fit it
+    fn init(&mut self) -> Result<(), ProblemError> {
+        self.width = self.problem.width();
+        self.height = self.problem.height();
         self.col_domains = vec![Domain::new(); self.width];
         self.row_domains = vec![Domain::new(); self.height];
         self.col_assignments = vec![None; self.width];
@@ -77,6 +111,16 @@ impl SolverBacktrack {
         self.solution_cnt = 0;
         self.solution = None;
         self.start = Instant::now();
+        self.timed_out = false;
+        self.cancelled = false;
+        self.problem.validate()?;
+        if self
+            .given
+            .as_ref()
+            .is_some_and(|given| !given.fits(&self.problem))
+        {
+            return Err(ProblemError::PartialGridMismatch);
+        }
 
         for col in 0..self.width {
             enumerate_domain(
@@ -84,7 +128,7 @@ impl SolverBacktrack {
                 0,
                 self.height,
                 0,
-                0,
+                Line::zeros(self.height),
                 &mut self.col_domains[col],
             );
         }
@@ -95,15 +139,30 @@ impl SolverBacktrack {
                 0,
                 self.width,
                 0,
-                0,
+                Line::zeros(self.width),
                 &mut self.row_domains[row],
             );
         }
+
+        if let Some(given) = &self.given {
+            for col in 0..self.width {
+                self.col_domains[col].restrict(&given.col(col));
+            }
+            for row in 0..self.height {
+                self.row_domains[row].restrict(&given.row(row));
+            }
+        }
+        Ok(())
     }
 
     // `true` iff there is some solution
     fn search(&mut self, solution_cnt_needed: u32) -> bool {
+        if self.cancel.is_cancelled() {
+            self.cancelled = true;
+            return false;
+        }
         if Instant::now() - self.start > self.timeout {
+            self.timed_out = true;
             return false;
         }
         if self.is_complete() {
@@ -121,10 +180,10 @@ impl SolverBacktrack {
         }
         .clone();
         for line_value in &domain.0 {
-            if self.is_assignment_consistent(&var_type, var_idx, *line_value) {
+            if self.is_assignment_consistent(&var_type, var_idx, line_value) {
                 match var_type {
-                    VarType::Column => self.col_assignments[var_idx] = Some(*line_value),
-                    VarType::Row => self.row_assignments[var_idx] = Some(*line_value),
+                    VarType::Column => self.col_assignments[var_idx] = Some(line_value.clone()),
+                    VarType::Row => self.row_assignments[var_idx] = Some(line_value.clone()),
                 };
 
                 if self.search(solution_cnt_needed) && self.solution_cnt >= solution_cnt_needed {
@@ -164,13 +223,13 @@ impl SolverBacktrack {
         res
     }
 
-    fn is_assignment_consistent(&self, var_type: &VarType, var_idx: usize, value: Line) -> bool {
+    fn is_assignment_consistent(&self, var_type: &VarType, var_idx: usize, value: &Line) -> bool {
         match var_type {
             VarType::Row => {
                 for col in 0..self.width {
                     if self.col_assignments[col].is_some()
-                        && (value & (1 << col) == 0)
-                            != (self.col_assignments[col].as_ref().unwrap() & (1 << var_idx) == 0)
+                        && value.get(col)
+                            != self.col_assignments[col].as_ref().unwrap().get(var_idx)
                     {
                         return false;
                     }
@@ -179,8 +238,8 @@ impl SolverBacktrack {
             VarType::Column => {
                 for row in 0..self.height {
                     if self.row_assignments[row].is_some()
-                        && (value & (1 << row) == 0)
-                            != (self.row_assignments[row].as_ref().unwrap() & (1 << var_idx) == 0)
+                        && value.get(row)
+                            != self.row_assignments[row].as_ref().unwrap().get(var_idx)
                     {
                         return false;
                     }
@@ -191,14 +250,13 @@ impl SolverBacktrack {
     }
 
     fn to_solution(&self) -> Solution {
-        let mut grid = vec![vec![false; self.width]; self.height];
-        for row in 0..self.height {
-            for col in 0..self.width {
-                if self.col_assignments[col].unwrap() & (1 << row) != 0 {
-                    grid[row][col] = true;
-                }
-            }
-        }
+        let grid = (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| self.col_assignments[col].as_ref().unwrap().get(row))
+                    .collect()
+            })
+            .collect();
         Solution {
             problem: self.problem.clone(),
             grid,
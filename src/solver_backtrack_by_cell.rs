@@ -1,8 +1,8 @@
 use std::time::{Duration, Instant};
 
 use crate::{
-    problem::Problem,
-    solver::{Solution, Solver, UniqueSolutionResult},
+    problem::{Problem, ProblemError},
+    solver::{CancelToken, Outcome, PartialGrid, Solution, SolutionCount, Solver},
 };
 
 #[derive(Clone)]
@@ -22,16 +22,22 @@ pub struct SolverBacktrackByCell {
     row_state: Vec<LineState>,
     solution_cnt: u32,
     solution: Option<Solution>,
+    given: Option<PartialGrid>,
     timeout: Duration,
     start: Instant,
+    timed_out: bool,
+    cancel: CancelToken,
+    cancelled: bool,
 }
 
 impl Solver for SolverBacktrackByCell {
     fn new(problem: &Problem) -> Self {
-        let width = problem.col_info.len();
-        let height = problem.row_info.len();
+        let mut problem = problem.clone();
+        problem.canonicalize();
+        let width = problem.width();
+        let height = problem.height();
         Self {
-            problem: problem.clone(),
+            problem,
             grid: vec![vec![false; width]; height],
             width,
             height,
@@ -39,8 +45,12 @@ impl Solver for SolverBacktrackByCell {
             row_state: vec![],
             solution_cnt: 0,
             solution: None,
+            given: None,
             timeout: Duration::from_secs(u64::MAX),
             start: Instant::now(),
+            timed_out: false,
+            cancel: CancelToken::new(),
+            cancelled: false,
         }
     }
 
@@ -49,33 +59,62 @@ impl Solver for SolverBacktrackByCell {
         self
     }
 
-    fn any_solution(&mut self) -> Option<Solution> {
-        self.init();
-        if self.search(0, 0, 1) {
-            return self.solution.clone();
+    fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    fn partial_grid(&mut self, grid: &PartialGrid) -> &mut Self {
+        self.given = Some(grid.clone());
+        self
+    }
+
+    fn any_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
-        None
+        self.search_from_start(1);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn unique_solution(&mut self) -> UniqueSolutionResult {
-        self.init();
-        self.search(0, 0, 2);
-        UniqueSolutionResult {
-            solution: self.solution.clone(),
-            is_unique: self.solution_cnt == 1,
+    fn unique_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
+        self.search_from_start(2);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn solution_cnt(&mut self) -> u32 {
-        self.init();
-        self.search(0, 0, u32::MAX);
-        self.solution_cnt
+    fn solution_cnt(&mut self) -> SolutionCount {
+        if self.init().is_ok() {
+            self.search_from_start(u32::MAX);
+        }
+        SolutionCount {
+            count: self.solution_cnt,
+            is_exact: !self.timed_out && !self.cancelled,
+        }
     }
 }
 
 impl SolverBacktrackByCell {
-    fn init(&mut self) {
+    // `Err` iff the problem is invalid or the given cells do not fit it
+    fn init(&mut self) -> Result<(), ProblemError> {
         self.start = Instant::now();
+        self.timed_out = false;
+        self.cancelled = false;
+        self.solution_cnt = 0;
+        self.solution = None;
+        self.problem.validate()?;
         self.col_state.clear();
         self.col_state.reserve(self.width);
         self.grid = vec![vec![false; self.width]; self.height];
@@ -105,12 +144,39 @@ impl SolverBacktrackByCell {
             });
             self.row_state[row].rest_1s.reverse();
         }
-        self.solution_cnt = 0;
-        self.solution = None;
+        match &self.given {
+            Some(given) if !given.fits(&self.problem) => Err(ProblemError::PartialGridMismatch),
+            _ => Ok(()),
+        }
+    }
+
+    // A grid without cells has just the empty solution, valid problems for
+    // it have no clues.
+    fn search_from_start(&mut self, solution_cnt_needed: u32) -> bool {
+        if self.width == 0 || self.height == 0 {
+            self.record_solution();
+            return true;
+        }
+        self.search(0, 0, solution_cnt_needed)
+    }
+
+    fn record_solution(&mut self) {
+        self.solution_cnt += 1;
+        if self.solution.is_none() {
+            self.solution = Some(Solution {
+                problem: self.problem.clone(),
+                grid: self.grid.clone(),
+            });
+        }
     }
 
     fn search(&mut self, c: usize, r: usize, solution_cnt_needed: u32) -> bool {
+        if self.cancel.is_cancelled() {
+            self.cancelled = true;
+            return false;
+        }
         if Instant::now() - self.start > self.timeout {
+            self.timed_out = true;
             return false;
         }
         let tmp_row_state = self.row_state[r].clone();
@@ -141,13 +207,7 @@ impl SolverBacktrackByCell {
                     return true;
                 }
             } else {
-                self.solution_cnt += 1;
-                if self.solution.is_none() {
-                    self.solution = Some(Solution {
-                        problem: self.problem.clone(),
-                        grid: self.grid.clone(),
-                    });
-                }
+                self.record_solution();
                 return true;
             }
 
@@ -168,13 +228,7 @@ impl SolverBacktrackByCell {
                     return true;
                 }
             } else {
-                self.solution_cnt += 1;
-                if self.solution.is_none() {
-                    self.solution = Some(Solution {
-                        problem: self.problem.clone(),
-                        grid: self.grid.clone(),
-                    });
-                }
+                self.record_solution();
                 return true;
             }
 
@@ -220,18 +274,25 @@ impl SolverBacktrackByCell {
     }
 
     fn is_assignment_valid(&self, c: usize, r: usize, value: bool) -> bool {
+        if self
+            .given
+            .as_ref()
+            .is_some_and(|given| !given.grid[r][c].admits(value))
+        {
+            return false;
+        }
         match value {
             true => {
-                return !self.col_state[c].need_0
+                !self.col_state[c].need_0
                     && !self.row_state[r].need_0
                     && !self.col_state[c].rest_1s.is_empty()
-                    && !self.row_state[r].rest_1s.is_empty();
+                    && !self.row_state[r].rest_1s.is_empty()
             }
             false => {
-                return self.col_state[c].rest_0s_before_1 > 0
+                self.col_state[c].rest_0s_before_1 > 0
                     && self.row_state[r].rest_0s_before_1 > 0
                     && !self.col_state[c].need_1
-                    && !self.row_state[r].need_1;
+                    && !self.row_state[r].need_1
             }
         }
     }
@@ -2,8 +2,8 @@ use std::time::{Duration, Instant};
 
 use crate::{
     csp::{enumerate_domain, Domain, Line, VarType},
-    problem::Problem,
-    solver::{Solution, Solver, UniqueSolutionResult},
+    problem::{Problem, ProblemError},
+    solver::{CancelToken, Outcome, PartialGrid, Solution, SolutionCount, Solver},
 };
 
 pub struct SolverBacktrackInference {
@@ -16,14 +16,20 @@ pub struct SolverBacktrackInference {
     row_assignments: Vec<Option<Line>>,
     solution_cnt: u32,
     solution: Option<Solution>,
+    given: Option<PartialGrid>,
     timeout: Duration,
     start: Instant,
+    timed_out: bool,
+    cancel: CancelToken,
+    cancelled: bool,
 }
 
 impl Solver for SolverBacktrackInference {
     fn new(problem: &Problem) -> Self {
+        let mut problem = problem.clone();
+        problem.canonicalize();
         Self {
-            problem: problem.clone(),
+            problem,
             width: 0,
             height: 0,
             col_domains: vec![],
@@ -32,8 +38,12 @@ impl Solver for SolverBacktrackInference {
             row_assignments: vec![],
             solution_cnt: 0,
             solution: None,
+            given: None,
             timeout: Duration::from_secs(u64::MAX),
             start: Instant::now(),
+            timed_out: false,
+            cancel: CancelToken::new(),
+            cancelled: false,
         }
     }
 
@@ -42,34 +52,58 @@ impl Solver for SolverBacktrackInference {
         self
     }
 
-    fn any_solution(&mut self) -> Option<Solution> {
-        self.init();
-        if self.search(1) {
-            return self.solution.clone();
+    fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    fn partial_grid(&mut self, grid: &PartialGrid) -> &mut Self {
+        self.given = Some(grid.clone());
+        self
+    }
+
+    fn any_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
-        None
+        self.search(1);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn unique_solution(&mut self) -> UniqueSolutionResult {
-        self.init();
-        self.search(2);
-        UniqueSolutionResult {
-            solution: self.solution.clone(),
-            is_unique: self.solution_cnt == 1,
+    fn unique_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
         }
+        self.search(2);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
     }
 
-    fn solution_cnt(&mut self) -> u32 {
-        self.init();
-        self.search(u32::MAX);
-        self.solution_cnt
+    fn solution_cnt(&mut self) -> SolutionCount {
+        if self.init().is_ok() {
+            self.search(u32::MAX);
+        }
+        SolutionCount {
+            count: self.solution_cnt,
+            is_exact: !self.timed_out && !self.cancelled,
+        }
     }
 }
 
 impl SolverBacktrackInference {
-    fn init(&mut self) {
-        self.width = self.problem.col_info.len();
-        self.height = self.problem.row_info.len();
+    // `Err` iff the problem is invalid or the given cells do not fit it
+    fn init(&mut self) -> Result<(), ProblemError> {
+        self.width = self.problem.width();
+        self.height = self.problem.height();
         self.col_domains = vec![Domain::new(); self.width];
         self.row_domains = vec![Domain::new(); self.height];
         self.col_assignments = vec![None; self.width];
@@ -77,6 +111,16 @@ impl SolverBacktrackInference {
         self.solution_cnt = 0;
         self.solution = None;
         self.start = Instant::now();
+        self.timed_out = false;
+        self.cancelled = false;
+        self.problem.validate()?;
+        if self
+            .given
+            .as_ref()
+            .is_some_and(|given| !given.fits(&self.problem))
+        {
+            return Err(ProblemError::PartialGridMismatch);
+        }
 
         for col in 0..self.width {
             enumerate_domain(
@@ -84,7 +128,7 @@ impl SolverBacktrackInference {
                 0,
                 self.height,
                 0,
-                0,
+                Line::zeros(self.height),
                 &mut self.col_domains[col],
             );
         }
@@ -95,16 +139,31 @@ impl SolverBacktrackInference {
                 0,
                 self.width,
                 0,
-                0,
+                Line::zeros(self.width),
                 &mut self.row_domains[row],
             );
         }
 
+        if let Some(given) = &self.given {
+            for col in 0..self.width {
+                self.col_domains[col].restrict(&given.col(col));
+            }
+            for row in 0..self.height {
+                self.row_domains[row].restrict(&given.row(row));
+            }
+        }
+
         self.inference();
+        Ok(())
     }
 
     fn search(&mut self, solution_cnt_needed: u32) -> bool {
+        if self.cancel.is_cancelled() {
+            self.cancelled = true;
+            return false;
+        }
         if Instant::now() - self.start > self.timeout {
+            self.timed_out = true;
             return false;
         }
         if self.is_complete() {
@@ -119,18 +178,26 @@ impl SolverBacktrackInference {
         let mut determined_rows = Vec::with_capacity(self.height);
         for col in 0..self.width {
             if self.col_domains[col].size() == 1 {
-                self.col_assignments[col] = Some(self.col_domains[col].0[0]);
+                self.col_assignments[col] = Some(self.col_domains[col].0[0].clone());
                 determined_cols.push(col);
             }
         }
         for row in 0..self.height {
             if self.row_domains[row].size() == 1 {
-                self.row_assignments[row] = Some(self.row_domains[row].0[0]);
+                self.row_assignments[row] = Some(self.row_domains[row].0[0].clone());
                 determined_rows.push(row);
             }
         }
 
-        let (var_type, var_idx) = self.select_unassigned_var();
+        let Some((var_type, var_idx)) = self.select_unassigned_var() else {
+            // the lines determined by inference complete the grid
+            self.solution_cnt += 1;
+            if self.solution.is_none() {
+                self.solution = Some(self.to_solution());
+            }
+            self.unassign(determined_cols, determined_rows);
+            return true;
+        };
         let domain = match var_type {
             VarType::Column => &self.col_domains[var_idx],
             VarType::Row => &self.row_domains[var_idx],
@@ -139,13 +206,13 @@ impl SolverBacktrackInference {
 
         for line_value in &domain.0 {
             match var_type {
-                VarType::Column => self.col_assignments[var_idx] = Some(*line_value),
-                VarType::Row => self.row_assignments[var_idx] = Some(*line_value),
+                VarType::Column => self.col_assignments[var_idx] = Some(line_value.clone()),
+                VarType::Row => self.row_assignments[var_idx] = Some(line_value.clone()),
             };
             let tmp_domains = (self.col_domains.clone(), self.row_domains.clone());
             match var_type {
-                VarType::Column => self.col_domains[var_idx] = Domain(vec![*line_value]),
-                VarType::Row => self.row_domains[var_idx] = Domain(vec![*line_value]),
+                VarType::Column => self.col_domains[var_idx] = Domain(vec![line_value.clone()]),
+                VarType::Row => self.row_domains[var_idx] = Domain(vec![line_value.clone()]),
             }
 
             self.inference_single_var(&var_type, var_idx);
@@ -162,14 +229,17 @@ impl SolverBacktrackInference {
             self.row_domains = tmp_domains.1;
         }
 
-        for col in determined_cols {
+        self.unassign(determined_cols, determined_rows);
+        false
+    }
+
+    fn unassign(&mut self, cols: Vec<usize>, rows: Vec<usize>) {
+        for col in cols {
             self.col_assignments[col] = None;
         }
-        for row in determined_rows {
+        for row in rows {
             self.row_assignments[row] = None;
         }
-
-        false
     }
 
     fn is_complete(&self) -> bool {
@@ -177,19 +247,20 @@ impl SolverBacktrackInference {
             && self.row_assignments.iter().all(|v| v.is_some())
     }
 
-    fn select_unassigned_var(&self) -> (VarType, usize) {
-        let mut res = (VarType::Column, 0);
+    // `None` iff every line is assigned
+    fn select_unassigned_var(&self) -> Option<(VarType, usize)> {
+        let mut res = None;
         let mut least = usize::MAX;
         for col in 0..self.width {
             if self.col_assignments[col].is_none() && self.col_domains[col].size() < least {
                 least = self.col_domains[col].size();
-                res = (VarType::Column, col);
+                res = Some((VarType::Column, col));
             }
         }
         for row in 0..self.height {
             if self.row_assignments[row].is_none() && self.row_domains[row].size() < least {
                 least = self.row_domains[row].size();
-                res = (VarType::Row, row);
+                res = Some((VarType::Row, row));
             }
         }
         res
@@ -200,7 +271,7 @@ impl SolverBacktrackInference {
             let mut delete_count = 0;
             // mask: positions that must be filled
             for col in 0..self.width {
-                let mut mask = Line::MAX;
+                let mut mask = Line::ones(self.height);
                 for value in &self.col_domains[col].0 {
                     mask &= value;
                 }
@@ -208,12 +279,12 @@ impl SolverBacktrackInference {
                     let before = self.row_domains[row].0.len();
                     self.row_domains[row]
                         .0
-                        .retain(|value| !(mask & (1 << row) != 0 && value & (1 << col) == 0));
+                        .retain(|value| !mask.get(row) || value.get(col));
                     delete_count += before - self.row_domains[row].0.len();
                 }
             }
             for row in 0..self.height {
-                let mut mask = Line::MAX;
+                let mut mask = Line::ones(self.width);
                 for value in &self.row_domains[row].0 {
                     mask &= value;
                 }
@@ -221,35 +292,35 @@ impl SolverBacktrackInference {
                     let before = self.col_domains[col].0.len();
                     self.col_domains[col]
                         .0
-                        .retain(|value| !(mask & (1 << col) != 0 && value & (1 << row) == 0));
+                        .retain(|value| !mask.get(col) || value.get(row));
                     delete_count += before - self.col_domains[col].0.len();
                 }
             }
 
             // mask: positions that must NOT be filled
             for col in 0..self.width {
-                let mut mask = Line::MAX;
+                let mut mask = Line::ones(self.height);
                 for value in &self.col_domains[col].0 {
-                    mask &= !value;
+                    mask.and_not_assign(value);
                 }
                 for row in 0..self.height {
                     let before = self.row_domains[row].0.len();
                     self.row_domains[row]
                         .0
-                        .retain(|value| !(mask & (1 << row) != 0 && value & (1 << col) != 0));
+                        .retain(|value| !mask.get(row) || !value.get(col));
                     delete_count += before - self.row_domains[row].0.len();
                 }
             }
             for row in 0..self.height {
-                let mut mask = Line::MAX;
+                let mut mask = Line::ones(self.width);
                 for value in &self.row_domains[row].0 {
-                    mask &= !value;
+                    mask.and_not_assign(value);
                 }
                 for col in 0..self.width {
                     let before = self.col_domains[col].0.len();
                     self.col_domains[col]
                         .0
-                        .retain(|value| !(mask & (1 << col) != 0 && value & (1 << row) != 0));
+                        .retain(|value| !mask.get(col) || !value.get(row));
                     delete_count += before - self.col_domains[col].0.len();
                 }
             }
@@ -263,19 +334,19 @@ impl SolverBacktrackInference {
     fn inference_single_var(&mut self, var_type: &VarType, var_idx: usize) {
         match var_type {
             VarType::Column => {
-                let mut mask_1 = Line::MAX;
-                let mut mask_0 = Line::MAX;
+                let mut mask_1 = Line::ones(self.height);
+                let mut mask_0 = Line::ones(self.height);
                 for value in &self.col_domains[var_idx].0 {
                     mask_1 &= value;
                 }
                 for value in &self.col_domains[var_idx].0 {
-                    mask_0 &= !value;
+                    mask_0.and_not_assign(value);
                 }
                 for row in 0..self.height {
                     let before = self.row_domains[row].0.len();
                     self.row_domains[row].0.retain(|value| {
-                        !(mask_1 & (1 << row) != 0 && value & (1 << var_idx) == 0)
-                            && !(mask_0 & (1 << row) != 0 && value & (1 << var_idx) != 0)
+                        (!mask_1.get(row) || value.get(var_idx))
+                            && (!mask_0.get(row) || !value.get(var_idx))
                     });
                     if before != self.row_domains[row].0.len() {
                         self.inference_single_var(&VarType::Row, row);
@@ -283,19 +354,19 @@ impl SolverBacktrackInference {
                 }
             }
             VarType::Row => {
-                let mut mask_1 = Line::MAX;
-                let mut mask_0 = Line::MAX;
+                let mut mask_1 = Line::ones(self.width);
+                let mut mask_0 = Line::ones(self.width);
                 for value in &self.row_domains[var_idx].0 {
                     mask_1 &= value;
                 }
                 for value in &self.row_domains[var_idx].0 {
-                    mask_0 &= !value;
+                    mask_0.and_not_assign(value);
                 }
                 for col in 0..self.width {
                     let before = self.col_domains[col].0.len();
                     self.col_domains[col].0.retain(|value| {
-                        !(mask_1 & (1 << col) != 0 && value & (1 << var_idx) == 0)
-                            && !(mask_0 & (1 << col) != 0 && value & (1 << var_idx) != 0)
+                        (!mask_1.get(col) || value.get(var_idx))
+                            && (!mask_0.get(col) || !value.get(var_idx))
                     });
                     if before != self.col_domains[col].0.len() {
                         self.inference_single_var(&VarType::Column, col);
@@ -306,14 +377,13 @@ impl SolverBacktrackInference {
     }
 
     fn to_solution(&self) -> Solution {
-        let mut grid = vec![vec![false; self.width]; self.height];
-        for row in 0..self.height {
-            for col in 0..self.width {
-                if self.col_assignments[col].unwrap() & (1 << row) != 0 {
-                    grid[row][col] = true;
-                }
-            }
-        }
+        let grid = (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| self.col_assignments[col].as_ref().unwrap().get(row))
+                    .collect()
+            })
+            .collect();
         Solution {
             problem: self.problem.clone(),
             grid,
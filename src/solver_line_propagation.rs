@@ -0,0 +1,231 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    csp::VarType,
+    line_solver::solve_line,
+    problem::{CellState, Problem, ProblemError},
+    solver::{CancelToken, Outcome, PartialGrid, Solution, SolutionCount, Solver},
+};
+
+pub struct SolverLinePropagation {
+    problem: Problem,
+    width: usize,
+    height: usize,
+    grid: Vec<Vec<CellState>>,
+    solution_cnt: u32,
+    solution: Option<Solution>,
+    given: Option<PartialGrid>,
+    timeout: Duration,
+    start: Instant,
+    timed_out: bool,
+    cancel: CancelToken,
+    cancelled: bool,
+}
+
+impl Solver for SolverLinePropagation {
+    fn new(problem: &Problem) -> Self {
+        let mut problem = problem.clone();
+        problem.canonicalize();
+        Self {
+            problem,
+            width: 0,
+            height: 0,
+            grid: vec![],
+            solution_cnt: 0,
+            solution: None,
+            given: None,
+            timeout: Duration::from_secs(u64::MAX),
+            start: Instant::now(),
+            timed_out: false,
+            cancel: CancelToken::new(),
+            cancelled: false,
+        }
+    }
+
+    fn timeout(&mut self, duration: Duration) -> &mut Self {
+        self.timeout = duration;
+        self
+    }
+
+    fn cancel_token(&mut self, token: &CancelToken) -> &mut Self {
+        self.cancel = token.clone();
+        self
+    }
+
+    fn partial_grid(&mut self, grid: &PartialGrid) -> &mut Self {
+        self.given = Some(grid.clone());
+        self
+    }
+
+    fn any_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
+        }
+        self.search(1);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
+    }
+
+    fn unique_solution(&mut self) -> Outcome {
+        if let Err(err) = self.init() {
+            return Outcome::Invalid(err);
+        }
+        self.search(2);
+        Outcome::from_search(
+            self.solution.clone(),
+            self.solution_cnt,
+            self.timed_out,
+            self.cancelled,
+        )
+    }
+
+    fn solution_cnt(&mut self) -> SolutionCount {
+        if self.init().is_ok() {
+            self.search(u32::MAX);
+        }
+        SolutionCount {
+            count: self.solution_cnt,
+            is_exact: !self.timed_out && !self.cancelled,
+        }
+    }
+}
+
+impl SolverLinePropagation {
+    // `Err` iff the problem is invalid or the given cells do not fit it
+    fn init(&mut self) -> Result<(), ProblemError> {
+        self.width = self.problem.width();
+        self.height = self.problem.height();
+        self.solution_cnt = 0;
+        self.solution = None;
+        self.start = Instant::now();
+        self.timed_out = false;
+        self.cancelled = false;
+        self.problem.validate()?;
+        match &self.given {
+            Some(given) if !given.fits(&self.problem) => {
+                return Err(ProblemError::PartialGridMismatch)
+            }
+            Some(given) => self.grid = given.grid.clone(),
+            None => self.grid = vec![vec![CellState::Unknown; self.width]; self.height],
+        }
+        Ok(())
+    }
+
+    // `true` iff there is some solution
+    fn search(&mut self, solution_cnt_needed: u32) -> bool {
+        if self.cancel.is_cancelled() {
+            self.cancelled = true;
+            return false;
+        }
+        if Instant::now() - self.start > self.timeout {
+            self.timed_out = true;
+            return false;
+        }
+        if !self.propagate() {
+            return false;
+        }
+
+        let Some((r, c)) = self.select_unknown_cell() else {
+            self.solution_cnt += 1;
+            if self.solution.is_none() {
+                self.solution = Some(self.to_solution());
+            }
+            return true;
+        };
+
+        let tmp_grid = self.grid.clone();
+        for value in [CellState::Filled, CellState::Empty] {
+            self.grid[r][c] = value;
+            if self.search(solution_cnt_needed) && self.solution_cnt >= solution_cnt_needed {
+                return true;
+            }
+            self.grid = tmp_grid.clone();
+        }
+
+        false
+    }
+
+    // Solves every row and column on its own until nothing changes.
+    // `false` iff some line contradicts its clue.
+    fn propagate(&mut self) -> bool {
+        let mut queued_rows = vec![true; self.height];
+        let mut queued_cols = vec![true; self.width];
+        let mut queue: VecDeque<(VarType, usize)> = (0..self.height)
+            .map(|row| (VarType::Row, row))
+            .chain((0..self.width).map(|col| (VarType::Column, col)))
+            .collect();
+
+        while let Some((var_type, var_idx)) = queue.pop_front() {
+            let (line_info, queued_crossing, crossing_type) = match var_type {
+                VarType::Row => {
+                    queued_rows[var_idx] = false;
+                    (
+                        &self.problem.row_info[var_idx],
+                        &mut queued_cols,
+                        VarType::Column,
+                    )
+                }
+                VarType::Column => {
+                    queued_cols[var_idx] = false;
+                    (
+                        &self.problem.col_info[var_idx],
+                        &mut queued_rows,
+                        VarType::Row,
+                    )
+                }
+            };
+            let line = self.line(&var_type, var_idx);
+            let Some(solved) = solve_line(line_info, &line) else {
+                return false;
+            };
+            for (cross_idx, (before, after)) in line.iter().zip(&solved).enumerate() {
+                if before == after {
+                    continue;
+                }
+                match var_type {
+                    VarType::Row => self.grid[var_idx][cross_idx] = *after,
+                    VarType::Column => self.grid[cross_idx][var_idx] = *after,
+                }
+                if !queued_crossing[cross_idx] {
+                    queued_crossing[cross_idx] = true;
+                    queue.push_back((crossing_type.clone(), cross_idx));
+                }
+            }
+        }
+        true
+    }
+
+    fn line(&self, var_type: &VarType, var_idx: usize) -> Vec<CellState> {
+        match var_type {
+            VarType::Row => self.grid[var_idx].clone(),
+            VarType::Column => self.grid.iter().map(|row| row[var_idx]).collect(),
+        }
+    }
+
+    fn select_unknown_cell(&self) -> Option<(usize, usize)> {
+        for (r, row) in self.grid.iter().enumerate() {
+            if let Some(c) = row.iter().position(|cell| *cell == CellState::Unknown) {
+                return Some((r, c));
+            }
+        }
+        None
+    }
+
+    fn to_solution(&self) -> Solution {
+        Solution {
+            problem: self.problem.clone(),
+            grid: self
+                .grid
+                .iter()
+                .map(|row| row.iter().map(|cell| *cell == CellState::Filled).collect())
+                .collect(),
+        }
+    }
+}
@@ -1,20 +1,51 @@
-use rand::random;
+use std::{
+    fs,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::{random, thread_rng};
 
 use crate::{
-    generator::{random_nonogram, random_nonogram_with_unique_solution},
-    problem::Problem,
-    solver::Solver,
+    batch::Batch,
+    csp::VarType,
+    format_non::{NonError, NonPuzzle},
+    format_xml::{read_puzzles, write_puzzles, XmlError, XmlPuzzle},
+    game::{Game, LineStatus, Verdict},
+    generator::{
+        random_nonogram, random_nonogram_with_unique_solution, seeded_rng, GeneratorConfig,
+    },
+    grader::{grade, Difficulty, Grader},
+    hint::{next_hint, Technique},
+    import::ImageImporter,
+    layout::Layout,
+    pdf::Booklet,
+    problem::{
+        decode_grid, encode_grid, CellState, DecodeError, Problem, ProblemError, MAX_DIMENSION,
+    },
+    puzzle_file::{is_puzzle_file, read_puzzle_file, PuzzleFileError},
+    raster::{Image, ImageError},
+    render_raster::RasterRenderer,
+    render_svg::SvgRenderer,
+    solver::{CancelToken, Outcome, PartialGrid, Solution, SolutionCount, Solver},
     solver_backtrack::SolverBacktrack,
     solver_backtrack_by_cell::SolverBacktrackByCell,
     solver_backtrack_inference::SolverBacktrackInference,
+    solver_line_propagation::SolverLinePropagation,
 };
 
 fn have_solution<T: Solver>() {
     for _ in 0..100 {
-        let problem = random_nonogram(random::<usize>() % 10 + 1, random::<usize>() % 10 + 1, 0.6);
+        let problem = random_nonogram(
+            random::<usize>() % 10 + 1,
+            random::<usize>() % 10 + 1,
+            0.6,
+            &mut thread_rng(),
+        );
         let mut solver = T::new(&problem);
         solver.any_solution();
-        let solution = solver.any_solution();
+        let solution = solver.any_solution().into_solution();
         assert!(
             solution.is_some() && solution.as_ref().unwrap().is_correct(),
             "{}",
@@ -36,13 +67,59 @@ fn have_two_solutions<T: Solver>() {
     ]);
     let mut solver = T::new(&problem);
     let result = solver.unique_solution();
-    assert!(!result.is_unique);
-    assert_eq!(solver.solution_cnt(), 2);
+    assert!(matches!(result, Outcome::Multiple(solution) if solution.is_correct()));
+    assert!(matches!(solver.any_solution(), Outcome::Solved(_)));
+    assert_eq!(
+        solver.solution_cnt(),
+        SolutionCount {
+            count: 2,
+            is_exact: true
+        }
+    );
+}
+
+fn respects_partial_grid<T: Solver>() {
+    let problem = Problem::from(
+        (0..8)
+            .map(|r| (0..8).map(|c| (r + c) % 2 == 0).collect())
+            .collect::<Vec<Vec<bool>>>(),
+    );
+    let mut grid = PartialGrid::new(&problem);
+    grid.grid[0][1] = CellState::Filled;
+    let Outcome::Solved(solution) = T::new(&problem).partial_grid(&grid).unique_solution() else {
+        panic!("not unique");
+    };
+    assert!(solution.is_correct());
+    assert!(solution.grid[0][1]);
+
+    // each mark alone is fine, together they rule out both solutions
+    grid.grid[2][2] = CellState::Filled;
+    assert!(grid.is_consistent());
+    assert!(matches!(
+        T::new(&problem).partial_grid(&grid).any_solution(),
+        Outcome::NoSolution
+    ));
+
+    grid.grid[0][2] = CellState::Filled;
+    assert!(!grid.is_consistent());
+    assert_eq!(grid.conflicting_lines(), vec![(VarType::Row, 0)]);
+    assert_eq!(T::new(&problem).partial_grid(&grid).solution_cnt().count, 0);
+
+    let smaller = PartialGrid::new(&Problem::empty(8, 7));
+    assert!(matches!(
+        T::new(&problem).partial_grid(&smaller).unique_solution(),
+        Outcome::Invalid(ProblemError::PartialGridMismatch)
+    ));
 }
 
 fn solution_cnt<T1: Solver, T2: Solver>() {
     for _ in 0..100 {
-        let problem = random_nonogram(random::<usize>() % 10 + 1, random::<usize>() % 10 + 1, 0.6);
+        let problem = random_nonogram(
+            random::<usize>() % 10 + 1,
+            random::<usize>() % 10 + 1,
+            0.6,
+            &mut thread_rng(),
+        );
         let mut solver1 = T1::new(&problem);
         let mut solver2 = T2::new(&problem);
         assert_eq!(
@@ -54,6 +131,142 @@ fn solution_cnt<T1: Solver, T2: Solver>() {
     }
 }
 
+fn rejects_invalid_problems<T: Solver>() {
+    let invalid = [
+        Problem::from_clues(vec![vec![1]], vec![vec![1], vec![-1]]),
+        Problem::from_clues(vec![vec![1]], vec![vec![1], vec![1, 0]]),
+        Problem::from_clues(vec![vec![1], vec![1]], vec![vec![i32::MAX, i32::MAX]]),
+        Problem::from_clues(vec![vec![1], vec![]], vec![vec![2], vec![]]),
+    ];
+    for problem in invalid {
+        let expected = problem.validate().unwrap_err();
+        assert!(matches!(
+            T::new(&problem).any_solution(),
+            Outcome::Invalid(err) if err == expected
+        ));
+        assert!(matches!(
+            T::new(&problem).unique_solution(),
+            Outcome::Invalid(err) if err == expected
+        ));
+        assert_eq!(
+            T::new(&problem).solution_cnt(),
+            SolutionCount {
+                count: 0,
+                is_exact: true
+            }
+        );
+    }
+}
+
+fn degenerate_problems() -> Vec<Problem> {
+    vec![
+        Problem::empty(0, 0),
+        Problem::empty(0, 3),
+        Problem::empty(3, 0),
+        Problem::empty(4, 3),
+        Problem::from(vec![vec![true]]),
+        Problem::from(vec![vec![false]]),
+        Problem::from_clues(vec![vec![1], vec![0]], vec![vec![0], vec![1]]),
+    ]
+}
+
+fn handles_degenerate_sizes<T: Solver>() {
+    for problem in degenerate_problems() {
+        let solution = T::new(&problem).any_solution().into_solution().unwrap();
+        assert!(solution.is_correct());
+        assert_eq!(solution.grid.len(), problem.height());
+        assert!(solution.grid.iter().all(|row| row.len() == problem.width()));
+        assert!(matches!(
+            T::new(&problem).unique_solution(),
+            Outcome::Solved(_)
+        ));
+        assert_eq!(T::new(&problem).solution_cnt().count, 1);
+    }
+}
+
+fn reports_timeouts<T: Solver>(size: usize) {
+    // every permutation matrix is a solution
+    let problem = Problem::new(vec![vec![1]; size], vec![vec![1]; size]).unwrap();
+    let mut solver = T::new(&problem);
+    solver.timeout(Duration::ZERO);
+    assert!(matches!(
+        solver.any_solution(),
+        Outcome::TimedOut { partial_count: 0 }
+    ));
+    assert!(matches!(
+        solver.unique_solution(),
+        Outcome::TimedOut { partial_count: 0 }
+    ));
+    assert!(!solver.solution_cnt().is_exact);
+}
+
+// `checks` search nodes have to find two solutions, but far too few to count
+// them for `size`.
+fn stops_when_cancelled<T: Solver>(size: usize, checks: u64) {
+    // clones count down together
+    let token = CancelToken::after_checks(2);
+    assert!(!token.is_cancelled() && !token.clone().is_cancelled());
+    assert!(token.is_cancelled());
+
+    let problem = Problem::new(vec![vec![1]; size], vec![vec![1]; size]).unwrap();
+    let token = CancelToken::new();
+    token.cancel();
+    let mut solver = T::new(&problem);
+    solver.cancel_token(&token);
+    assert!(matches!(
+        solver.any_solution(),
+        Outcome::Cancelled { partial_count: 0 }
+    ));
+    assert!(!solver.solution_cnt().is_exact);
+
+    // enough nodes to tell there are several, far too few to count them
+    let mut solver = T::new(&problem);
+    solver.timeout(Duration::from_secs(600));
+    solver.cancel_token(&CancelToken::after_checks(checks));
+    assert!(matches!(solver.any_solution(), Outcome::Solved(_)));
+    solver.cancel_token(&CancelToken::after_checks(checks));
+    assert!(matches!(solver.unique_solution(), Outcome::Multiple(_)));
+    let token = CancelToken::after_checks(checks);
+    let cnt = solver.cancel_token(&token).solution_cnt();
+    assert!(token.is_cancelled());
+    assert!(cnt.count > 0 && !cnt.is_exact);
+    // the token stays cancelled
+    assert!(matches!(
+        solver.unique_solution(),
+        Outcome::Cancelled { partial_count: 0 }
+    ));
+
+    // cancelled through a clone from another thread
+    let token = CancelToken::new();
+    let clone = token.clone();
+    thread::spawn(move || clone.cancel()).join().unwrap();
+    assert!(matches!(
+        T::new(&problem).cancel_token(&token).unique_solution(),
+        Outcome::Cancelled { partial_count: 0 }
+    ));
+}
+
+fn large_problem(width: usize, height: usize) -> Problem {
+    Problem::from(
+        (0..height)
+            .map(|r| (0..width).map(|c| (r * 3 + c) % 7 != 0).collect())
+            .collect::<Vec<Vec<bool>>>(),
+    )
+}
+
+fn solve_large<T: Solver>() {
+    for (width, height) in [(80, 100), (120, 120)] {
+        let problem = large_problem(width, height);
+        let mut solver = T::new(&problem);
+        let Outcome::Solved(solution) = solver.unique_solution() else {
+            panic!("not unique");
+        };
+        assert_eq!(solution.grid.len(), height);
+        assert_eq!(solution.grid[0].len(), width);
+        assert!(solution.is_correct());
+    }
+}
+
 #[test]
 fn solvers_test() {
     have_solution::<SolverBacktrack>();
@@ -62,17 +275,1419 @@ fn solvers_test() {
     have_two_solutions::<SolverBacktrackInference>();
     have_solution::<SolverBacktrackByCell>();
     have_two_solutions::<SolverBacktrackByCell>();
+    have_solution::<SolverLinePropagation>();
+    have_two_solutions::<SolverLinePropagation>();
+    respects_partial_grid::<SolverBacktrack>();
+    respects_partial_grid::<SolverBacktrackInference>();
+    respects_partial_grid::<SolverBacktrackByCell>();
+    respects_partial_grid::<SolverLinePropagation>();
+    rejects_invalid_problems::<SolverBacktrack>();
+    rejects_invalid_problems::<SolverBacktrackInference>();
+    rejects_invalid_problems::<SolverBacktrackByCell>();
+    rejects_invalid_problems::<SolverLinePropagation>();
+    handles_degenerate_sizes::<SolverBacktrack>();
+    handles_degenerate_sizes::<SolverBacktrackInference>();
+    handles_degenerate_sizes::<SolverBacktrackByCell>();
+    handles_degenerate_sizes::<SolverLinePropagation>();
+    reports_timeouts::<SolverBacktrack>(7);
+    reports_timeouts::<SolverBacktrackInference>(20);
+    reports_timeouts::<SolverBacktrackByCell>(20);
+    reports_timeouts::<SolverLinePropagation>(20);
+    stops_when_cancelled::<SolverBacktrack>(7, 100_000);
+    stops_when_cancelled::<SolverBacktrackInference>(20, 10_000);
+    stops_when_cancelled::<SolverBacktrackByCell>(20, 10_000);
+    stops_when_cancelled::<SolverLinePropagation>(20, 10_000);
     solution_cnt::<SolverBacktrack, SolverBacktrackInference>();
     solution_cnt::<SolverBacktrackInference, SolverBacktrackByCell>();
+    solution_cnt::<SolverBacktrackInference, SolverLinePropagation>();
 }
 
 #[test]
+fn large_solvers_test() {
+    solve_large::<SolverBacktrack>();
+    solve_large::<SolverBacktrackInference>();
+    solve_large::<SolverLinePropagation>();
+}
 
+#[test]
 fn generator_test() {
     for _ in 0..100 {
-        let problem = random_nonogram_with_unique_solution(20, 20, 0.6);
+        let problem = random_nonogram_with_unique_solution(20, 20, 0.6, &mut thread_rng());
         let mut solver = SolverBacktrackInference::new(&problem);
-        let result = solver.unique_solution();
-        assert!(result.is_unique);
+        assert!(matches!(solver.unique_solution(), Outcome::Solved(_)));
+    }
+}
+
+#[test]
+fn partial_grid_test() {
+    let problem = random_nonogram(7, 5, 0.5, &mut thread_rng());
+    let solution = SolverBacktrackInference::new(&problem)
+        .any_solution()
+        .into_solution()
+        .unwrap();
+
+    let mut grid = PartialGrid::new(&problem);
+    assert!(!grid.is_complete());
+    assert!(grid.is_consistent());
+    assert!(grid.to_solution().is_none());
+    for r in 0..5 {
+        for c in 0..7 {
+            if solution.grid[r][c] {
+                grid.grid[r][c] = CellState::Filled;
+            }
+        }
     }
+    assert!(grid.is_consistent());
+    assert!(format!("{}", grid).contains("▇▇ "));
+
+    let full = PartialGrid::from(solution.clone());
+    assert!(full.is_complete());
+    assert!(full.is_consistent());
+    assert_eq!(full.to_solution().unwrap().grid, solution.grid);
+    assert_eq!(
+        format!("{}", full.to_solution().unwrap()),
+        format!("{}", solution)
+    );
+
+    let problem = Problem::from(vec![vec![true, false], vec![false, false]]);
+    let mut grid = PartialGrid::new(&problem);
+    grid.grid[0][0] = CellState::Empty;
+    assert!(grid.is_consistent());
+    grid.grid[1][0] = CellState::Filled;
+    assert!(!grid.is_consistent());
+    assert!(format!("{}", grid).contains("▔╳▏"));
+}
+
+#[test]
+fn hint_test() {
+    let problem = Problem::from(vec![
+        vec![true, true, true, true, false],
+        vec![false, false, true, false, false],
+        vec![true, false, true, false, true],
+    ]);
+    let hint = next_hint(&PartialGrid::new(&problem)).unwrap();
+    assert_eq!(hint.technique, Technique::Overlap);
+    assert_eq!(hint.line, (VarType::Row, 2));
+    assert_eq!(hint.cells.len(), 5);
+    assert_eq!(
+        hint.reason,
+        "Row 3 (clue 1 1 1): with every block pushed as far left and as far right as it goes, \
+         both positions of a block cover cells 1, 3, 5, which must be filled; \
+         no block can reach cells 2, 4, which must be empty."
+    );
+
+    // overlap gets stuck on this one after a few steps
+    let problem = Problem::from(vec![
+        vec![true, false, false, true, false, false],
+        vec![false, false, true, false, true, true],
+        vec![false, false, false, true, false, true],
+    ]);
+    let mut grid = PartialGrid::new(&problem);
+    let hint = loop {
+        let hint = next_hint(&grid).unwrap();
+        if hint.technique == Technique::LineSolving {
+            break hint;
+        }
+        for (r, c, state) in hint.cells {
+            grid.grid[r][c] = state;
+        }
+    };
+    assert_eq!(hint.line, (VarType::Row, 2));
+    assert_eq!(hint.cells, vec![(2, 2, CellState::Empty)]);
+    assert_eq!(
+        hint.reason,
+        "Row 3 (clue 1 1): every way to place the blocks that fits the marked cells \
+         leaves cell 3 empty."
+    );
+
+    for _ in 0..20 {
+        let problem = random_nonogram_with_unique_solution(8, 8, 0.5, &mut thread_rng());
+        let solution = SolverLinePropagation::new(&problem)
+            .any_solution()
+            .into_solution()
+            .unwrap();
+        let mut grid = PartialGrid::new(&problem);
+        while let Some(hint) = next_hint(&grid) {
+            for (r, c, state) in hint.cells {
+                assert!(state.admits(solution.grid[r][c]));
+                grid.grid[r][c] = state;
+            }
+        }
+        assert!(grid.is_consistent());
+    }
+}
+
+#[test]
+fn grader_test() {
+    let grade_of = |row_info: Vec<Vec<i32>>, col_info: Vec<Vec<i32>>| {
+        grade(&Problem::from_clues(row_info, col_info))
+    };
+
+    let result = grade_of(vec![vec![2], vec![1]], vec![vec![2], vec![1]]).unwrap();
+    assert_eq!(result.difficulty, Difficulty::Easy);
+    assert_eq!(result.steps, vec![(Technique::Overlap, 4)]);
+    assert_eq!(result.score, 4);
+
+    let result = grade_of(
+        vec![
+            vec![1, 1, 1],
+            vec![2, 2],
+            vec![2, 3],
+            vec![2, 1, 1],
+            vec![1, 1],
+            vec![1],
+            vec![1, 4],
+        ],
+        vec![
+            vec![2, 1, 1],
+            vec![4, 1],
+            vec![1, 1],
+            vec![2, 1, 1],
+            vec![4, 1],
+            vec![1, 1],
+            vec![1, 1],
+        ],
+    )
+    .unwrap();
+    assert_eq!(result.difficulty, Difficulty::Medium);
+    assert_eq!(
+        result.techniques(),
+        vec![Technique::Overlap, Technique::LineSolving]
+    );
+    assert_eq!(result.branches, 0);
+
+    let result = grade_of(
+        vec![vec![1], vec![1], vec![2], vec![2]],
+        vec![vec![1], vec![1], vec![1, 1], vec![2]],
+    )
+    .unwrap();
+    assert_eq!(result.difficulty, Difficulty::Hard);
+    assert!(result.techniques().contains(&Technique::Probing));
+    assert!(result.score > 16);
+
+    // two solutions
+    assert!(grade_of(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).is_none());
+    // no solution
+    assert!(grade_of(vec![vec![2], vec![]], vec![vec![1], vec![]]).is_none());
+
+    // every permutation matrix is a solution, only guessing tells
+    let problem = Problem::new(vec![vec![1]; 12], vec![vec![1]; 12]).unwrap();
+    let too_hard = |grader: &Grader| grader.grade(&problem).unwrap().difficulty;
+    assert_eq!(too_hard(Grader::new().branch_limit(3)), Difficulty::TooHard);
+    assert_eq!(
+        too_hard(Grader::new().timeout(Duration::ZERO)),
+        Difficulty::TooHard
+    );
+    let token = CancelToken::new();
+    token.cancel();
+    assert_eq!(
+        too_hard(Grader::new().cancel_token(&token)),
+        Difficulty::TooHard
+    );
+    assert!(Grader::new().grade(&problem).is_none());
+    assert!(Grader::new()
+        .timeout(Duration::MAX)
+        .grade(&problem)
+        .is_none());
+
+    for _ in 0..20 {
+        let problem = random_nonogram_with_unique_solution(8, 8, 0.5, &mut thread_rng());
+        let result = grade(&problem).unwrap();
+        assert_eq!(
+            result.difficulty,
+            Difficulty::from(*result.techniques().last().unwrap())
+        );
+    }
+}
+
+#[test]
+fn generator_rectangular_test() {
+    let mut rng = thread_rng();
+    let sizes = (1..=7)
+        .flat_map(|width| (1..=7).map(move |height| (width, height)))
+        .chain([(12, 3), (3, 12), (20, 1), (1, 20), (15, 9)]);
+    for (width, height) in sizes {
+        for expected_density in [0.0, 0.3, 0.6, 1.0] {
+            let problem =
+                random_nonogram_with_unique_solution(width, height, expected_density, &mut rng);
+            assert_eq!(problem.col_info.len(), width);
+            assert_eq!(problem.row_info.len(), height);
+
+            let result = SolverLinePropagation::new(&problem).unique_solution();
+            assert!(
+                matches!(result, Outcome::Solved(_)),
+                "{}x{}: {}",
+                width,
+                height,
+                problem
+            );
+            let filled: usize = result
+                .into_solution()
+                .unwrap()
+                .grid
+                .iter()
+                .map(|row| row.iter().filter(|cell| **cell).count())
+                .sum();
+            let density = filled as f64 / (width * height) as f64;
+            assert!(
+                density >= expected_density,
+                "{}x{}: density {} < {}",
+                width,
+                height,
+                density,
+                expected_density
+            );
+        }
+    }
+
+    let problem = random_nonogram_with_unique_solution(0, 4, 0.5, &mut rng);
+    assert!(problem.col_info.is_empty());
+    assert_eq!(problem.row_info, vec![Vec::<i32>::new(); 4]);
+    let problem = random_nonogram_with_unique_solution(4, 4, -1.0, &mut rng);
+    assert_eq!(problem.row_info.len(), 4);
+}
+
+#[test]
+fn generator_config_test() {
+    for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+        let problem = GeneratorConfig::new(8, 6)
+            .difficulty(difficulty)
+            .generate()
+            .unwrap();
+        assert_eq!(problem.col_info.len(), 8);
+        assert_eq!(problem.row_info.len(), 6);
+        assert_eq!(grade(&problem).unwrap().difficulty, difficulty);
+    }
+
+    let problem = GeneratorConfig::new(10, 10)
+        .density(0.6)
+        .difficulty_range(Difficulty::Medium, Difficulty::Expert)
+        .generate()
+        .unwrap();
+    assert!(grade(&problem).unwrap().difficulty >= Difficulty::Medium);
+
+    assert!(GeneratorConfig::new(0, 5).generate().is_none());
+
+    // grading a single 30x30 grid takes longer than the timeout
+    let start = Instant::now();
+    assert!(GeneratorConfig::new(30, 30)
+        .difficulty(Difficulty::Expert)
+        .timeout(Duration::from_millis(200))
+        .generate()
+        .is_none());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(GeneratorConfig::new(4, 4)
+        .timeout(Duration::MAX)
+        .generate()
+        .is_some());
+    assert!(GeneratorConfig::new(8, 6)
+        .difficulty_range(Difficulty::Easy, Difficulty::TooHard)
+        .timeout(Duration::ZERO)
+        .generate()
+        .is_none());
+}
+
+#[test]
+fn seeded_generator_test() {
+    // fixed output guards against changes in how random numbers are drawn
+    let problem = random_nonogram(6, 4, 0.5, &mut seeded_rng(4711));
+    assert_eq!(
+        problem.row_info,
+        vec![vec![1], vec![1, 1], vec![3], vec![3]]
+    );
+    assert_eq!(
+        problem.col_info,
+        vec![vec![2], vec![1, 1], vec![2], vec![1, 1], vec![1], vec![]]
+    );
+
+    let problem = random_nonogram_with_unique_solution(5, 5, 0.5, &mut seeded_rng(4711));
+    assert_eq!(
+        problem.row_info,
+        vec![vec![3], vec![1, 1], vec![1, 1], vec![1, 1], vec![2, 2]]
+    );
+    assert_eq!(
+        problem.col_info,
+        vec![vec![3, 1], vec![1, 2], vec![2], vec![1, 1], vec![2]]
+    );
+
+    let problem = GeneratorConfig::new(6, 6)
+        .difficulty(Difficulty::Medium)
+        .seed(4711)
+        .generate()
+        .unwrap();
+    assert_eq!(
+        problem.row_info,
+        vec![
+            vec![1, 1],
+            vec![1],
+            vec![2, 3],
+            vec![1, 3],
+            vec![1, 2],
+            vec![4]
+        ]
+    );
+    // a timeout that does not expire changes nothing
+    let timed = GeneratorConfig::new(6, 6)
+        .difficulty(Difficulty::Medium)
+        .seed(4711)
+        .timeout(Duration::from_secs(600))
+        .generate()
+        .unwrap();
+    assert_eq!(timed, problem);
+
+    for seed in 0..10 {
+        let first = random_nonogram_with_unique_solution(8, 8, 0.5, &mut seeded_rng(seed));
+        let second = random_nonogram_with_unique_solution(8, 8, 0.5, &mut seeded_rng(seed));
+        assert_eq!(first.row_info, second.row_info);
+        assert_eq!(first.col_info, second.col_info);
+    }
+}
+
+#[test]
+fn format_non_test() {
+    let text = "catalogue \"test\"
+title \"Heart\"
+by \"someone\"
+width 5
+height 4
+
+rows
+1,1
+5
+3
+1
+
+columns
+1
+3
+3
+3
+1
+
+goal \"01010111110111000100\"
+";
+    let puzzle: NonPuzzle = text.parse().unwrap();
+    assert_eq!(puzzle.title.as_deref(), Some("Heart"));
+    assert_eq!(puzzle.author.as_deref(), Some("someone"));
+    assert_eq!(
+        puzzle.problem.row_info,
+        vec![vec![1, 1], vec![5], vec![3], vec![1]]
+    );
+    assert_eq!(
+        puzzle.problem.col_info,
+        vec![vec![1], vec![3], vec![3], vec![3], vec![1]]
+    );
+    let goal = puzzle.goal.as_ref().unwrap();
+    assert!(goal.is_correct());
+    assert_eq!(goal.grid[0], vec![false, true, false, true, false]);
+
+    let written = puzzle.to_string();
+    let reread: NonPuzzle = written.parse().unwrap();
+    assert_eq!(reread.title, puzzle.title);
+    assert_eq!(reread.problem.row_info, puzzle.problem.row_info);
+    assert_eq!(reread.problem.col_info, puzzle.problem.col_info);
+    assert_eq!(reread.goal.unwrap().grid, goal.grid);
+
+    let mut tricky = NonPuzzle::from(puzzle.problem.clone());
+    tricky.title = Some("\"Two\" lines\nrows\r\n".to_string());
+    tricky.author = Some("a \"quoted\"\tname".to_string());
+    let reread: NonPuzzle = tricky.to_string().parse().unwrap();
+    assert_eq!(reread.title.as_deref(), Some("\"Two\" lines rows  "));
+    assert_eq!(reread.author.as_deref(), Some("a \"quoted\" name"));
+    assert_eq!(reread.problem, puzzle.problem);
+
+    let problem = random_nonogram(6, 3, 0.3, &mut thread_rng());
+    let reread: NonPuzzle = NonPuzzle::from(problem.clone())
+        .to_string()
+        .parse()
+        .unwrap();
+    assert_eq!(reread.problem.row_info, problem.row_info);
+    assert_eq!(reread.problem.col_info, problem.col_info);
+    assert!(reread.goal.is_none());
+
+    let parse = |text: &str| text.parse::<NonPuzzle>().err();
+    assert_eq!(
+        parse("rows\n1\n1 x\ncolumns\n1"),
+        Some(NonError::InvalidClue {
+            line: 3,
+            text: "1 x".to_string()
+        })
+    );
+    assert_eq!(
+        parse("rows\n1,-2\ncolumns\n1"),
+        Some(NonError::InvalidClue {
+            line: 2,
+            text: "1,-2".to_string()
+        })
+    );
+    assert_eq!(
+        parse("width five\nrows\n1\ncolumns\n1"),
+        Some(NonError::InvalidNumber {
+            line: 1,
+            text: "five".to_string()
+        })
+    );
+    assert_eq!(
+        parse("rows\n1\n"),
+        Some(NonError::MissingSection("columns"))
+    );
+    assert_eq!(
+        parse("width 2\nrows\n1\ncolumns\n1"),
+        Some(NonError::ClueCountMismatch {
+            section: "columns",
+            expected: 2,
+            found: 1
+        })
+    );
+    assert_eq!(
+        parse("rows\n1\ncolumns\n1\ngoal 2"),
+        Some(NonError::InvalidGoal {
+            line: 5,
+            text: "2".to_string()
+        })
+    );
+    assert_eq!(
+        parse("rows\n1\ncolumns\n1\ngoal 0"),
+        Some(NonError::WrongGoal)
+    );
+}
+
+#[test]
+fn format_xml_test() {
+    let xml = r##"<?xml version="1.0"?>
+<!DOCTYPE pbn SYSTEM "https://webpbn.com/pbn-0.3.dtd">
+<puzzleset>
+ <source>test</source>
+ <puzzle type="grid" defaultcolor="black">
+  <id>#1</id>
+  <title>Heart &amp; soul</title>
+  <color name="white" char="-">fff</color>
+  <color name="black" char="#">000</color>
+  <clues type="columns">
+   <line><count>1</count></line>
+   <line><count>3</count></line>
+   <line><count>3</count></line>
+   <line><count>3</count></line>
+   <line><count>1</count></line>
+  </clues>
+  <clues type="rows">
+   <line><count>1</count><count color="black">1</count></line>
+   <line><count>5</count></line>
+   <line><count>3</count></line>
+   <line><count>1</count></line>
+  </clues>
+  <solution type="goal">
+   <image>
+   |-#-#-|
+   |#####|
+   |-###-|
+   |--#--|
+   </image>
+  </solution>
+ </puzzle>
+ <puzzle>
+  <clues type="rows"><line/><line><count>2</count></line></clues>
+  <clues type="columns"><line><count>1</count></line><line><count>1</count></line></clues>
+ </puzzle>
+</puzzleset>
+"##;
+    let read_all = |xml: &str| {
+        read_puzzles(xml)
+            .unwrap()
+            .into_iter()
+            .collect::<Result<Vec<XmlPuzzle>, XmlError>>()
+            .unwrap()
+    };
+    let puzzles = read_all(xml);
+    assert_eq!(puzzles.len(), 2);
+    let heart = &puzzles[0];
+    assert_eq!(heart.id.as_deref(), Some("#1"));
+    assert_eq!(heart.title.as_deref(), Some("Heart & soul"));
+    assert_eq!(
+        heart.problem.row_info,
+        vec![vec![1, 1], vec![5], vec![3], vec![1]]
+    );
+    let goal = heart.solution.as_ref().unwrap();
+    assert_eq!(goal.grid[3], vec![false, false, true, false, false]);
+    assert_eq!(puzzles[1].problem.row_info, vec![vec![], vec![2]]);
+    assert!(puzzles[1].solution.is_none());
+
+    let reread = read_all(&write_puzzles(&puzzles));
+    assert_eq!(reread.len(), 2);
+    assert_eq!(reread[0].title, heart.title);
+    assert_eq!(reread[0].problem.col_info, heart.problem.col_info);
+    assert_eq!(reread[0].solution.as_ref().unwrap().grid, goal.grid);
+    assert_eq!(reread[1].problem.row_info, puzzles[1].problem.row_info);
+
+    let problem = random_nonogram(7, 4, 0.4, &mut thread_rng());
+    let reread = read_all(&write_puzzles(&[XmlPuzzle::from(problem.clone())]));
+    assert_eq!(reread[0].problem.row_info, problem.row_info);
+    assert_eq!(reread[0].problem.col_info, problem.col_info);
+
+    let single = r#"<puzzle><clues type="rows"><line><count>1</count></line></clues>
+        <clues type="columns"><line><count>1</count></line></clues></puzzle>"#;
+    assert_eq!(read_all(single).len(), 1);
+
+    // unsupported puzzles fail on their own, not the whole collection
+    let mixed = format!(
+        "<puzzleset><puzzle type=\"triddler\"/>{}\
+         <puzzle><clues type=\"rows\"><line><count color=\"red\">1</count></line></clues></puzzle>\
+         </puzzleset>",
+        &single
+    );
+    let puzzles = read_puzzles(&mixed).unwrap();
+    assert_eq!(puzzles.len(), 3);
+    assert_eq!(
+        puzzles[0].as_ref().err(),
+        Some(&XmlError::UnsupportedType("triddler".to_string()))
+    );
+    assert_eq!(puzzles[1].as_ref().unwrap().problem.row_info, vec![vec![1]]);
+    assert_eq!(
+        puzzles[2].as_ref().err(),
+        Some(&XmlError::MultipleColors("red".to_string()))
+    );
+
+    let parse = |xml: &str| {
+        read_puzzles(xml)
+            .and_then(|mut puzzles| puzzles.remove(0))
+            .err()
+    };
+    assert!(matches!(parse("<puzzleset>"), Some(XmlError::Parse(_))));
+    assert_eq!(
+        parse("<puzzle type=\"triddler\"/>"),
+        Some(XmlError::UnsupportedType("triddler".to_string()))
+    );
+    assert_eq!(
+        parse("<puzzle><clues type=\"rows\"/></puzzle>"),
+        Some(XmlError::MissingClues("columns"))
+    );
+    assert_eq!(
+        parse("<puzzle><clues type=\"rows\"><line><count color=\"red\">1</count></line></clues></puzzle>"),
+        Some(XmlError::MultipleColors("red".to_string()))
+    );
+    assert_eq!(
+        parse("<puzzle><clues type=\"rows\"><line><count>0</count></line></clues></puzzle>"),
+        Some(XmlError::InvalidCount("0".to_string()))
+    );
+    let with_image = |image: &str| {
+        format!(
+            "<puzzle><clues type=\"rows\"><line><count>1</count></line></clues>\
+             <clues type=\"columns\"><line><count>1</count></line></clues>\
+             <solution><image>{}</image></solution></puzzle>",
+            image
+        )
+    };
+    assert!(matches!(
+        parse(&with_image("|X.|")),
+        Some(XmlError::InvalidImage(_))
+    ));
+    assert!(matches!(
+        parse(&with_image("|?|")),
+        Some(XmlError::InvalidImage(_))
+    ));
+    assert_eq!(parse(&with_image("|.|")), Some(XmlError::WrongSolution));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_test() {
+    use crate::solver::Solution;
+
+    let json = r#"{"width":2,"height":3,"col_info":[[1],[1,1]],"row_info":[[2],[],[1]]}"#;
+    let problem: Problem = serde_json::from_str(json).unwrap();
+    assert_eq!((problem.width(), problem.height()), (2, 3));
+    assert_eq!(problem.col_info, vec![vec![1], vec![1, 1]]);
+    assert_eq!(problem.row_info, vec![vec![2], vec![], vec![1]]);
+    assert_eq!(serde_json::to_string(&problem).unwrap(), json);
+    // documents without dimensions still load, wrong dimensions do not
+    let old: Problem =
+        serde_json::from_str(r#"{"col_info":[[1],[1,1]],"row_info":[[2],[],[1]]}"#).unwrap();
+    assert_eq!(old, problem);
+    let err = serde_json::from_str::<Problem>(
+        r#"{"width":2,"height":2,"col_info":[[1]],"row_info":[[1]]}"#,
+    )
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("expected clues for 2 columns, found 1"));
+
+    let mut solver = SolverBacktrack::new(&problem);
+    let result = solver.unique_solution();
+    let json = serde_json::to_string(&result).unwrap();
+    assert_eq!(
+        json,
+        r#"{"solved":{"problem":{"width":2,"height":3,"col_info":[[1],[1,1]],"row_info":[[2],[],[1]]},"grid":[[true,true],[false,false],[false,true]]}}"#
+    );
+    let reread: Outcome = serde_json::from_str(&json).unwrap();
+    assert!(matches!(reread, Outcome::Solved(_)));
+    assert_eq!(
+        reread.into_solution().unwrap().grid,
+        result.into_solution().unwrap().grid
+    );
+    let outcomes = [
+        Outcome::NoSolution,
+        Outcome::TimedOut { partial_count: 1 },
+        Outcome::Invalid(ProblemError::FilledCountMismatch { rows: 2, cols: 3 }),
+    ];
+    assert_eq!(
+        serde_json::to_string(&outcomes).unwrap(),
+        r#"["no_solution",{"timed_out":{"partial_count":1}},{"invalid":{"filled_count_mismatch":{"rows":2,"cols":3}}}]"#
+    );
+    let count = SolutionCount {
+        count: 2,
+        is_exact: false,
+    };
+    let json = serde_json::to_string(&count).unwrap();
+    assert_eq!(json, r#"{"count":2,"is_exact":false}"#);
+    assert_eq!(serde_json::from_str::<SolutionCount>(&json).unwrap(), count);
+
+    let problem = random_nonogram(8, 5, 0.5, &mut thread_rng());
+    let solution = SolverBacktrack::new(&problem)
+        .any_solution()
+        .into_solution()
+        .unwrap();
+    let reread: Solution =
+        serde_json::from_str(&serde_json::to_string(&solution).unwrap()).unwrap();
+    assert_eq!(reread.problem.col_info, problem.col_info);
+    assert_eq!(reread.problem.row_info, problem.row_info);
+    assert_eq!(reread.grid, solution.grid);
+    assert!(reread.is_correct());
+
+    let mut config = GeneratorConfig::new(6, 4);
+    config
+        .difficulty_range(Difficulty::Medium, Difficulty::Hard)
+        .seed(7);
+    let json = serde_json::to_value(&config).unwrap();
+    assert_eq!(json["min_difficulty"], "medium");
+    assert_eq!(json["max_difficulty"], "hard");
+    assert_eq!(json["seed"], 7);
+    let reread: GeneratorConfig = serde_json::from_value(json).unwrap();
+    assert_eq!(reread.width, 6);
+    assert_eq!(reread.height, 4);
+    assert_eq!(reread.min_difficulty, Difficulty::Medium);
+    assert_eq!(reread.seed, Some(7));
+    assert_eq!(
+        reread.generate().unwrap().row_info,
+        config.generate().unwrap().row_info
+    );
+}
+
+#[test]
+fn encoding_test() {
+    let grid = vec![
+        vec![false, true, false, true, false],
+        vec![true, true, true, true, true],
+        vec![false, true, true, true, false],
+        vec![false, false, true, false, false],
+    ];
+    let problem = Problem::from(grid.clone());
+    let encoded = problem.encode();
+    assert!(encoded
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_eq!(Problem::decode(&encoded).unwrap(), problem);
+
+    let encoded = encode_grid(&grid);
+    assert_eq!(decode_grid(&encoded).unwrap(), grid);
+    assert_eq!(Problem::decode(&encoded).unwrap(), problem);
+
+    for (width, height) in [(1, 1), (7, 3), (13, 17), (40, 2)] {
+        let problem = random_nonogram(width, height, 0.5, &mut thread_rng());
+        assert_eq!(Problem::decode(&problem.encode()).unwrap(), problem);
+        let grid: Vec<Vec<bool>> = (0..height)
+            .map(|_| (0..width).map(|_| random()).collect())
+            .collect();
+        assert_eq!(decode_grid(&encode_grid(&grid)).unwrap(), grid);
+    }
+
+    let empty = Problem::empty(3, 0);
+    assert_eq!(Problem::decode(&empty.encode()).unwrap(), empty);
+    let decoded = decode_grid(&encode_grid(&[vec![], vec![]])).unwrap();
+    assert_eq!(decoded, vec![Vec::<bool>::new(); 2]);
+
+    assert_eq!(
+        Problem::decode("AQ!"),
+        Err(DecodeError::InvalidCharacter('!'))
+    );
+    // version 2
+    assert_eq!(
+        Problem::decode("AgA"),
+        Err(DecodeError::UnsupportedVersion(2))
+    );
+    // version 1, kind 7
+    assert_eq!(Problem::decode("AQc"), Err(DecodeError::UnknownKind(7)));
+    assert_eq!(Problem::decode("AQ"), Err(DecodeError::Truncated));
+    // dimensions of 2^63 each, their sum overflows
+    assert_eq!(
+        Problem::decode("AQCAgICAgICAgIABgICAgICAgICAAQ"),
+        Err(DecodeError::InvalidNumber)
+    );
+    // grids of 2^40 x 0 and 0 x 2^40 cells need no data but a lot of memory
+    assert_eq!(
+        Problem::decode("AQGAgICAgCAA"),
+        Err(DecodeError::InvalidNumber)
+    );
+    assert_eq!(decode_grid("AQEAgICAgIAg"), Err(DecodeError::InvalidNumber));
+    let widest = Problem::empty(MAX_DIMENSION, 0);
+    assert_eq!(Problem::decode(&widest.encode()).unwrap(), widest);
+    assert_eq!(
+        Problem::decode(&Problem::empty(MAX_DIMENSION + 1, 0).encode()),
+        Err(DecodeError::InvalidNumber)
+    );
+    assert_eq!(
+        decode_grid(&problem.encode()),
+        Err(DecodeError::UnknownKind(0))
+    );
+    let mut encoded = problem.encode();
+    encoded.truncate(encoded.len() - 2);
+    assert_eq!(Problem::decode(&encoded), Err(DecodeError::Truncated));
+    encoded = encode_grid(&grid);
+    encoded.push_str("AA");
+    assert_eq!(Problem::decode(&encoded), Err(DecodeError::TrailingData));
+    let too_long = Problem::from_clues(vec![vec![1], vec![1]], vec![vec![1, 1]]);
+    assert_eq!(
+        Problem::decode(&too_long.encode()),
+        Err(DecodeError::ClueDoesNotFit(VarType::Column, 0))
+    );
+    let zero = Problem::from_clues(vec![vec![]], vec![vec![0]]);
+    assert_eq!(
+        Problem::decode(&zero.encode()),
+        Err(DecodeError::InvalidNumber)
+    );
+}
+
+#[test]
+fn render_svg_test() {
+    let problem = Problem::from(vec![
+        vec![false, true, false, true, false, false],
+        vec![true, true, true, true, true, false],
+        vec![false, true, true, true, false, true],
+        vec![false, false, true, false, false, true],
+    ]);
+    let layout = Layout::new(&problem, 10);
+    assert_eq!((layout.row_clue_cells, layout.col_clue_cells), (2, 1));
+    assert_eq!((layout.grid_left(), layout.grid_top()), (5, 5));
+    assert_eq!((layout.grid_right(), layout.grid_bottom()), (65, 45));
+    assert_eq!((layout.image_width(), layout.image_height()), (90, 60));
+    let labels = layout.clue_labels(&problem);
+    // row 3 is `3 1`, left aligned next to the grid
+    assert!(labels.iter().any(|l| (l.x, l.y, l.num) == (65, 25, 3)));
+    assert!(labels.iter().any(|l| (l.x, l.y, l.num) == (75, 25, 1)));
+    // column 2 is `3`, below the grid
+    assert!(labels.iter().any(|l| (l.x, l.y, l.num) == (15, 45, 3)));
+    let bold: Vec<bool> = layout.grid_lines().iter().map(|l| l.bold).collect();
+    assert_eq!(
+        bold,
+        vec![true, false, false, false, false, true, true, true, false, false, false, true]
+    );
+
+    let puzzle = SvgRenderer::new(&problem).render();
+    let document = roxmltree::Document::parse(&puzzle).unwrap();
+    let count = |name: &str| {
+        document
+            .descendants()
+            .filter(|node| node.has_tag_name(name))
+            .count()
+    };
+    let clue_cnt: usize = problem
+        .col_info
+        .iter()
+        .chain(&problem.row_info)
+        .map(|line| line.len())
+        .sum();
+    assert_eq!(count("text"), clue_cnt);
+    assert_eq!(count("line"), 7 + 5);
+    assert_eq!(count("rect"), 1);
+
+    let solution = SolverBacktrack::new(&problem)
+        .any_solution()
+        .into_solution()
+        .unwrap();
+    let answer = SvgRenderer::new(&problem)
+        .cell_size(16)
+        .solution(&solution)
+        .render();
+    let document = roxmltree::Document::parse(&answer).unwrap();
+    let filled = solution.grid.iter().flatten().filter(|cell| **cell).count();
+    assert_eq!(
+        document
+            .descendants()
+            .filter(|node| node.has_tag_name("rect"))
+            .count(),
+        1 + filled
+    );
+
+    let tiny = SvgRenderer::new(&problem).cell_size(0).render();
+    assert_eq!(tiny, SvgRenderer::new(&problem).cell_size(1).render());
+}
+
+#[test]
+fn render_raster_test() {
+    let problem = Problem::from(vec![
+        vec![false, true, false, true, false, false],
+        vec![true, true, true, true, true, false],
+        vec![false, true, true, true, false, true],
+        vec![false, false, true, false, false, true],
+    ]);
+    let layout = Layout::new(&problem, 10);
+    let puzzle = RasterRenderer::new(&problem).cell_size(10).render();
+    assert_eq!(
+        (puzzle.width, puzzle.height),
+        (layout.image_width(), layout.image_height())
+    );
+    let cell_center = |r: usize, c: usize| {
+        let (x, y) = layout.cell(r, c);
+        (x + 5, y + 5)
+    };
+    let (x, y) = cell_center(1, 1);
+    assert_eq!(puzzle.get(x, y), 255);
+    // a grid line and a margin pixel
+    assert_eq!(puzzle.get(layout.grid_left(), y), 0);
+    assert_eq!(puzzle.get(0, 0), 255);
+    // every clue cell has ink, the clue-free corner has none
+    for label in layout.clue_labels(&problem) {
+        let ink = (label.x..label.x + 10)
+            .flat_map(|x| (label.y..label.y + 10).map(move |y| (x, y)))
+            .filter(|(x, y)| puzzle.get(*x, *y) == 0)
+            .count();
+        assert!(ink > 0);
+    }
+    assert!(
+        (layout.grid_right() + 1..layout.image_width())
+            .all(|x| (layout.grid_bottom() + 1..layout.image_height())
+                .all(|y| puzzle.get(x, y) == 255))
+    );
+
+    let solution = SolverBacktrack::new(&problem)
+        .any_solution()
+        .into_solution()
+        .unwrap();
+    let answer = RasterRenderer::new(&problem)
+        .cell_size(10)
+        .solution(&solution)
+        .render();
+    for (r, row) in solution.grid.iter().enumerate() {
+        for (c, cell) in row.iter().enumerate() {
+            let (x, y) = cell_center(r, c);
+            assert_eq!(answer.get(x, y), if *cell { 0 } else { 255 });
+        }
+    }
+
+    let pgm = answer.to_pgm();
+    let header = format!("P5\n{} {}\n255\n", answer.width, answer.height);
+    assert!(pgm.starts_with(header.as_bytes()));
+    assert_eq!(pgm.len(), header.len() + answer.pixels.len());
+    let pbm = answer.to_pbm();
+    let header = format!("P4\n{} {}\n", answer.width, answer.height);
+    assert!(pbm.starts_with(header.as_bytes()));
+    let row_len = (answer.width as usize).div_ceil(8);
+    assert_eq!(pbm.len(), header.len() + row_len * answer.height as usize);
+    let (x, y) = cell_center(1, 0);
+    assert_ne!(
+        pbm[header.len() + y as usize * row_len + x as usize / 8] & (0x80 >> (x % 8)),
+        0
+    );
+    #[cfg(feature = "png")]
+    assert!(answer.to_png().unwrap().starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn import_test() {
+    let heart = vec![
+        vec![false, true, false, true, false],
+        vec![true, true, true, true, true],
+        vec![false, true, true, true, false],
+        vec![false, false, true, false, false],
+    ];
+    let plain = b"P1\n# heart\n5 4\n0 1 0 1 0\n11111\n0 1 1 1 0\n0 0 1 0 0\n";
+    let image = Image::decode(plain).unwrap();
+    assert_eq!((image.width, image.height), (5, 4));
+    let imported = ImageImporter::new().import(&image);
+    assert_eq!(imported.solution.grid, heart);
+    assert_eq!(imported.problem, Problem::from(heart.clone()));
+    assert_eq!(imported.is_unique, Some(true));
+
+    // the same picture, every pixel doubled and drawn in dark gray
+    let mut big = Image::new(10, 8, 255);
+    for (r, row) in heart.iter().enumerate() {
+        for (c, _) in row.iter().enumerate().filter(|(_, cell)| **cell) {
+            big.fill_rect(2 * c as u32, 2 * r as u32, 2, 2, 60);
+        }
+    }
+    for bytes in [big.to_pgm(), big.to_pbm()] {
+        let image = Image::decode(&bytes).unwrap();
+        let imported = ImageImporter::new().size(5, 4).import(&image);
+        assert_eq!(imported.solution.grid, heart);
+    }
+    // too light for a low threshold
+    let light = ImageImporter::new().threshold(50).grid(&big);
+    assert!(light.iter().flatten().all(|cell| !cell));
+    // upsampling repeats pixels
+    let grid = ImageImporter::new().size(10, 8).grid(&image);
+    assert_eq!(grid[7][4], heart[3][2]);
+    assert!(grid[7][5]);
+
+    let ascii_gray = b"P2 2 2 15 0 15 15 0";
+    let imported = ImageImporter::new().import(&Image::decode(ascii_gray).unwrap());
+    assert_eq!(
+        imported.solution.grid,
+        vec![vec![true, false], vec![false, true]]
+    );
+    assert_eq!(imported.is_unique, Some(false));
+    // not telling is not the same as not unique
+    let imported = ImageImporter::new()
+        .timeout(Duration::ZERO)
+        .import(&Image::decode(ascii_gray).unwrap());
+    assert_eq!(imported.is_unique, None);
+
+    let wide = Image::decode(b"P5\n2 1\n65535\n\x00\x00\xff\xff").unwrap();
+    assert_eq!(wide.pixels, vec![0, 255]);
+    assert_eq!(Image::decode(b"GIF89a"), Err(ImageError::UnsupportedFormat));
+    assert_eq!(Image::decode(b"P1 x 2"), Err(ImageError::InvalidHeader));
+    assert_eq!(
+        Image::decode(b"P1 2 2 0 1 2 0"),
+        Err(ImageError::InvalidPixel)
+    );
+    assert_eq!(
+        Image::decode(b"P5 2 2 255 \x00"),
+        Err(ImageError::Truncated)
+    );
+
+    #[cfg(feature = "png")]
+    assert_eq!(Image::decode(&big.to_png().unwrap()).unwrap(), big);
+}
+
+#[test]
+fn pdf_test() {
+    let mut booklet = Booklet::new();
+    booklet
+        .title("Weekly (42)")
+        .puzzles_per_page(2)
+        .answers_per_page(4);
+    for seed in 0..3 {
+        let problem = random_nonogram_with_unique_solution(8, 6, 0.5, &mut seeded_rng(seed));
+        booklet.problem(&problem);
+    }
+    let solution = SolverBacktrack::new(&large_problem(12, 10))
+        .any_solution()
+        .into_solution()
+        .unwrap();
+    booklet.solution(&solution);
+    booklet.problem(&Problem::from_clues(vec![vec![]], vec![vec![1]]));
+    let pdf = booklet.render();
+    let text = String::from_utf8(pdf.clone()).unwrap();
+    assert!(text.starts_with("%PDF-1.4\n"));
+    assert!(text.ends_with("%%EOF\n"));
+
+    // 5 puzzles, 2 per page, and 4 answers per page
+    assert!(text.contains("/Count 5 "));
+    assert_eq!(text.matches("/Type /Page ").count(), 5);
+    assert!(text.contains("(Weekly \\(42\\)) Tj"));
+    assert_eq!(text.matches("(Puzzles) Tj").count(), 2);
+    assert_eq!(text.matches("(Answers) Tj").count(), 2);
+    assert!(text.contains("(5 / 5) Tj"));
+    for num in 1..=5 {
+        assert_eq!(text.matches(&format!("({}.) Tj", num)).count(), 2);
+    }
+    assert_eq!(text.matches("(invalid puzzle) Tj").count(), 1);
+
+    // every permutation matrix is a solution, none is found in time
+    let mut booklet = Booklet::new();
+    booklet
+        .timeout(Duration::ZERO)
+        .problem(&Problem::new(vec![vec![1]; 20], vec![vec![1]; 20]).unwrap())
+        .solution(&solution);
+    let notes = String::from_utf8(booklet.render()).unwrap();
+    assert_eq!(notes.matches("(no solution found) Tj").count(), 1);
+    assert!(!notes.contains("(no solution) Tj"));
+    // two solutions, printing either would be wrong
+    let mut booklet = Booklet::new();
+    booklet.problem(&Problem::new(vec![vec![1]; 2], vec![vec![1]; 2]).unwrap());
+    let notes = String::from_utf8(booklet.render()).unwrap();
+    assert_eq!(notes.matches("(not unique) Tj").count(), 1);
+
+    // every cross reference points at its object
+    let xref = text[text.rfind("startxref\n").unwrap() + 10..]
+        .lines()
+        .next()
+        .unwrap()
+        .parse::<usize>()
+        .unwrap();
+    assert!(text[xref..].starts_with("xref\n0 14\n"));
+    for (idx, entry) in text[xref..].lines().skip(3).take(13).enumerate() {
+        let offset = entry[..10].parse::<usize>().unwrap();
+        assert!(text[offset..].starts_with(&format!("{} 0 obj\n", idx + 1)));
+    }
+    for stream in text.split(">>\nstream\n").skip(1) {
+        let (content, _) = stream.split_once("endstream").unwrap();
+        assert!(!content.is_empty());
+    }
+}
+
+#[test]
+fn problem_validation_test() {
+    let problem = Problem::new(
+        vec![vec![1, 1], vec![3], vec![]],
+        vec![vec![2], vec![1], vec![2]],
+    );
+    assert_eq!(
+        problem,
+        Ok(Problem::from_clues(
+            vec![vec![1, 1], vec![3], vec![]],
+            vec![vec![2], vec![1], vec![2]]
+        ))
+    );
+    let problem = problem.unwrap();
+    assert_eq!((problem.width(), problem.height()), (3, 3));
+    let mut wider = problem.clone();
+    wider.col_info.push(vec![]);
+    assert_eq!(
+        wider.validate(),
+        Err(ProblemError::LineCountMismatch {
+            line: VarType::Column,
+            expected: 3,
+            found: 4
+        })
+    );
+    assert_eq!(
+        wider.validate().unwrap_err().to_string(),
+        "expected clues for 3 columns, found 4"
+    );
+    // still drawn, with the lines it has
+    assert!(wider.to_string().lines().count() > 3);
+    let mut shorter = problem.clone();
+    shorter.row_info.pop();
+    assert_eq!(
+        shorter.validate(),
+        Err(ProblemError::LineCountMismatch {
+            line: VarType::Row,
+            expected: 3,
+            found: 2
+        })
+    );
+    assert!(matches!(
+        SolverLinePropagation::new(&shorter).any_solution(),
+        Outcome::Invalid(ProblemError::LineCountMismatch { .. })
+    ));
+    assert_eq!(
+        Problem::new(vec![vec![1, 0]], vec![vec![1], vec![]]),
+        Err(ProblemError::NonPositiveClue {
+            line: VarType::Row,
+            idx: 0,
+            num: 0
+        })
+    );
+    assert_eq!(
+        Problem::new(vec![vec![1], vec![1]], vec![vec![-3]]),
+        Err(ProblemError::NonPositiveClue {
+            line: VarType::Column,
+            idx: 0,
+            num: -3
+        })
+    );
+    assert_eq!(
+        Problem::new(vec![vec![2, 1], vec![]], vec![vec![1]; 3]),
+        Err(ProblemError::ClueTooLong {
+            line: VarType::Row,
+            idx: 0,
+            min_len: 4,
+            len: 3
+        })
+    );
+    let err = Problem::new(vec![vec![1]], vec![vec![i32::MAX, i32::MAX]]).unwrap_err();
+    assert_eq!(
+        err,
+        ProblemError::ClueTooLong {
+            line: VarType::Column,
+            idx: 0,
+            min_len: 2 * i32::MAX as u64 + 1,
+            len: 1
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "column 1: clue needs 4294967295 cells, the line has 1"
+    );
+    assert_eq!(
+        Problem::new(vec![vec![2], vec![1]], vec![vec![2], vec![]]),
+        Err(ProblemError::FilledCountMismatch { rows: 3, cols: 2 })
+    );
+    assert!(grade(&Problem::from_clues(
+        vec![vec![1], vec![1], vec![1]],
+        vec![vec![1], vec![1]]
+    ))
+    .is_none());
+    let problem = random_nonogram(9, 7, 0.5, &mut thread_rng());
+    assert_eq!(problem.validate(), Ok(()));
+}
+
+#[test]
+fn degenerate_sizes_test() {
+    assert_eq!(
+        Problem::new(vec![vec![0], vec![2]], vec![vec![1], vec![0, 1]]),
+        Err(ProblemError::NonPositiveClue {
+            line: VarType::Column,
+            idx: 1,
+            num: 0
+        })
+    );
+    let problem = Problem::new(vec![vec![0], vec![1]], vec![vec![], vec![0], vec![1]]).unwrap();
+    assert_eq!(problem.row_info, vec![vec![], vec![1]]);
+    assert_eq!(problem.col_info, vec![vec![], vec![], vec![1]]);
+    assert_eq!((problem.width(), problem.height()), (3, 2));
+
+    for problem in degenerate_problems() {
+        let solution = SolverLinePropagation::new(&problem)
+            .any_solution()
+            .into_solution()
+            .unwrap();
+        let mut canonical = problem.clone();
+        canonical.canonicalize();
+        assert_eq!(solution.problem, canonical);
+        assert!(problem.to_string().lines().count() > problem.height());
+        assert!(solution.to_string().lines().count() > problem.height());
+        assert!(PartialGrid::new(&problem).to_string().lines().count() > problem.height());
+        assert!(grade(&problem).is_some());
+        assert!(next_hint(&PartialGrid::from(solution.clone())).is_none());
+
+        let svg = SvgRenderer::new(&problem).solution(&solution).render();
+        roxmltree::Document::parse(&svg).unwrap();
+        let image = RasterRenderer::new(&problem).solution(&solution).render();
+        let layout = Layout::new(&problem, 12);
+        assert_eq!(
+            (image.width, image.height),
+            (layout.image_width(), layout.image_height())
+        );
+        let mut booklet = Booklet::new();
+        booklet.problem(&problem).solution(&solution);
+        assert!(booklet.render().ends_with(b"%%EOF\n"));
+
+        assert_eq!(Problem::decode(&canonical.encode()).unwrap(), canonical);
+        let reread: NonPuzzle = NonPuzzle::from(solution.clone())
+            .to_string()
+            .parse()
+            .unwrap();
+        assert_eq!(reread.problem, canonical);
+        let reread = read_puzzles(&write_puzzles(&[XmlPuzzle::from(solution)])).unwrap();
+        assert_eq!(reread[0].as_ref().unwrap().problem, canonical);
+    }
+
+    // the grid alone carries no width without rows
+    let solution = Solution {
+        problem: Problem::empty(3, 0),
+        grid: vec![],
+    };
+    assert!(solution.is_correct());
+    let wrong_size = Solution {
+        problem: Problem::empty(3, 1),
+        grid: vec![vec![false; 2]],
+    };
+    assert!(!wrong_size.is_correct());
+}
+
+#[test]
+fn batch_test() {
+    let dir = std::env::temp_dir().join(format!("nonogram-batch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("set")).unwrap();
+    let heart = vec![
+        vec![false, true, false, true, false],
+        vec![true, true, true, true, true],
+        vec![false, true, true, true, false],
+        vec![false, false, true, false, false],
+    ];
+    let heart = Problem::from(heart);
+    let two = Problem::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    fs::write(
+        dir.join("heart.non"),
+        NonPuzzle::from(heart.clone()).to_string(),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("set/pair.xml"),
+        write_puzzles(&[XmlPuzzle::from(two.clone()), XmlPuzzle::from(heart.clone())]),
+    )
+    .unwrap();
+    fs::write(dir.join("set/dot.pbm"), b"P1 1 1 1").unwrap();
+    fs::write(dir.join("bad, \"quoted\".non"), "rows\n3\ncolumns\n1\n").unwrap();
+    fs::write(dir.join("notes.txt"), "not a puzzle").unwrap();
+
+    assert!(is_puzzle_file(Path::new("a/b.PBN")));
+    assert!(!is_puzzle_file(&dir.join("notes.txt")));
+    assert_eq!(
+        read_puzzle_file(&dir.join("set/pair.xml")).unwrap().len(),
+        2
+    );
+    assert!(matches!(
+        read_puzzle_file(&dir.join("missing.non")),
+        Err(PuzzleFileError::Io(_))
+    ));
+    assert!(matches!(
+        read_puzzle_file(&dir.join("notes.txt")),
+        Err(PuzzleFileError::Non(_))
+    ));
+
+    let report = Batch::new()
+        .timeout(Duration::from_secs(10))
+        .run::<SolverLinePropagation>(&dir)
+        .unwrap();
+    let names: Vec<&str> = report.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "bad, \"quoted\".non",
+            "heart.non",
+            "set/dot.pbm",
+            "set/pair.xml#1",
+            "set/pair.xml#2"
+        ]
+    );
+    let bad = &report.entries[0];
+    assert!(bad.error.is_some() && bad.solution_cnt == 0);
+    let pair = &report.entries[3];
+    assert_eq!((pair.width, pair.height), (2, 2));
+    assert_eq!((pair.solution_cnt, pair.is_unique), (2, Some(false)));
+    for entry in [&report.entries[1], &report.entries[4]] {
+        assert_eq!((entry.solution_cnt, entry.is_unique), (1, Some(true)));
+        assert!(!entry.timed_out && entry.error.is_none());
+    }
+    let summary = report.summary();
+    assert_eq!(
+        (summary.puzzles, summary.unique, summary.multiple),
+        (5, 3, 1)
+    );
+    assert_eq!(
+        (summary.no_solution, summary.timed_out, summary.errors),
+        (0, 0, 1)
+    );
+
+    let csv = report.to_csv();
+    assert_eq!(csv.lines().count(), 6);
+    assert!(csv.starts_with("name,width,height,time_ms,solution_cnt,unique,timed_out,error\n"));
+    assert!(csv.contains("\n\"bad, \"\"quoted\"\".non\",0,0,"));
+    assert!(csv
+        .lines()
+        .any(|line| line.starts_with("set/pair.xml#1,2,2,") && line.ends_with(",2,false,false,")));
+    let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+    assert_eq!(json["summary"]["unique"], 3);
+    assert_eq!(json["puzzles"][0]["name"], "bad, \"quoted\".non");
+    assert_eq!(json["puzzles"][0]["unique"], serde_json::Value::Null);
+    assert_eq!(json["puzzles"][1]["solution_cnt"], 1);
+    assert_eq!(json["puzzles"][3]["unique"], false);
+
+    // the report is as good as the solver
+    let report = Batch::new().run::<SolverBacktrackInference>(&dir).unwrap();
+    assert_eq!(report.summary().unique, 3);
+    assert!(Batch::new()
+        .run::<SolverBacktrack>(&dir.join("missing"))
+        .is_err());
+
+    // an unsupported puzzle in a collection is one error entry
+    let mixed = write_puzzles(&[XmlPuzzle::from(two.clone())]).replace(
+        "<puzzle type=\"grid\"",
+        "<puzzle type=\"triddler\"/><puzzle type=\"grid\"",
+    );
+    fs::write(dir.join("set/pair.xml"), mixed).unwrap();
+    let report = Batch::new()
+        .run::<SolverLinePropagation>(&dir.join("set"))
+        .unwrap();
+    let names: Vec<&str> = report.entries.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["dot.pbm", "pair.xml#1", "pair.xml#2"]);
+    assert_eq!(
+        report.entries[1].error.as_deref(),
+        Some("unsupported puzzle type `triddler`")
+    );
+    assert_eq!(report.entries[2].solution_cnt, 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn game_test() {
+    let heart = vec![
+        vec![false, true, false, true, false],
+        vec![true, true, true, true, true],
+        vec![false, true, true, true, false],
+        vec![false, false, true, false, false],
+    ];
+    let problem = Problem::from(heart.clone());
+    let mut game = Game::new(&problem);
+    game.move_cursor(-3, 7);
+    assert_eq!(game.cursor, (0, 4));
+    game.move_cursor(2, -1);
+    assert_eq!(game.cursor, (2, 3));
+
+    game.toggle(CellState::Filled);
+    assert_eq!(game.board.grid[2][3], CellState::Filled);
+    game.toggle(CellState::Empty);
+    assert_eq!(game.board.grid[2][3], CellState::Empty);
+    game.toggle(CellState::Empty);
+    assert_eq!(game.board.grid[2][3], CellState::Unknown);
+    assert!(game.undo() && game.undo());
+    assert_eq!(game.board.grid[2][3], CellState::Filled);
+    assert!(game.redo());
+    assert_eq!(game.board.grid[2][3], CellState::Empty);
+    // a new move drops what could be redone
+    game.toggle(CellState::Filled);
+    assert!(!game.redo());
+    assert!(game.undo() && game.undo() && game.undo());
+    assert!(!game.undo());
+    assert_eq!(game.board.grid[2][3], CellState::Unknown);
+
+    // row 3 needs its middle cell to join the two into `3`
+    game.apply(vec![(2, 1, CellState::Filled), (2, 3, CellState::Filled)]);
+    let (rows, cols) = game.line_status();
+    assert_eq!(rows[2], LineStatus::Open);
+    assert_eq!(cols[3], LineStatus::Open);
+    game.apply(vec![(2, 2, CellState::Empty)]);
+    let (rows, _) = game.line_status();
+    assert_eq!(rows[2], LineStatus::Violated);
+    assert_eq!(
+        game.verify(),
+        Verdict::Violated(vec![(VarType::Row, 2), (VarType::Column, 2)])
+    );
+    assert!(game.hint().is_none());
+    assert!(game.undo());
+    game.apply(vec![(2, 2, CellState::Filled)]);
+    let (rows, _) = game.line_status();
+    assert_eq!(rows[2], LineStatus::Complete);
+    assert_eq!(game.verify(), Verdict::Consistent);
+
+    // consistent lines, but no solution: the bottom cell of column 3 is filled
+    let mut wrong = Game::new(&problem);
+    wrong.apply(vec![(3, 2, CellState::Empty)]);
+    assert!(wrong.board.conflicting_lines().is_empty());
+    assert_eq!(wrong.verify(), Verdict::NoSolution);
+
+    // hints alone finish the puzzle, one move each
+    let mut moves = 0;
+    while let Some(reason) = game.hint() {
+        assert!(!reason.is_empty());
+        moves += 1;
+    }
+    assert!(moves > 0);
+    assert_eq!(game.verify(), Verdict::Solved);
+    assert_eq!(game.board.to_solution().unwrap().grid, heart);
+    let (rows, cols) = game.line_status();
+    assert!(rows.iter().chain(&cols).all(|s| *s == LineStatus::Complete));
+    for _ in 0..moves {
+        assert!(game.undo());
+    }
+    assert_eq!(game.verify(), Verdict::Consistent);
+
+    // hints fall back to the solver when no single line helps
+    let two = Problem::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]).unwrap();
+    let mut game = Game::new(&two);
+    game.move_cursor(1, 1);
+    assert!(game.hint().is_some());
+    assert_ne!(game.board.grid[1][1], CellState::Unknown);
+    while game.hint().is_some() {}
+    assert!(game.board.is_complete());
+    assert_eq!(game.verify(), Verdict::Solved);
+    assert!(game.hint().is_none());
+
+    let mut empty = Game::new(&Problem::empty(0, 0));
+    empty.move_cursor(1, 1);
+    empty.toggle(CellState::Filled);
+    assert!(empty.hint().is_none());
+    assert_eq!(empty.verify(), Verdict::Solved);
 }
@@ -0,0 +1,158 @@
+// A terminal frontend for `Game`, drawn with the block characters of the
+// `Display` impls: row clues right of the grid, column clues below it.
+// Complete clues are dimmed and violated ones red.
+
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+
+use crate::{
+    csp::VarType,
+    game::{Game, LineStatus, Verdict},
+    problem::{CellState, Problem},
+    solver::Solution,
+};
+
+const HELP: &str = "arrows/hjkl move  space fill  x cross  del clear  \
+                    u undo  r redo  ? hint  v verify  q quit";
+
+// Runs until the player quits. Returns the board if it was solved.
+pub fn play(problem: &Problem) -> io::Result<Option<Solution>> {
+    let mut stdout = io::stdout();
+    enable_raw_mode()?;
+    let _guard = TerminalGuard;
+    execute!(stdout, EnterAlternateScreen, Hide)?;
+    run(&mut stdout, &mut Game::new(problem))
+}
+
+// Restores the terminal when dropped, so neither an error nor a panic leaves
+// it in raw mode. Errors while restoring are ignored, there is no better
+// place to report them.
+struct TerminalGuard;
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+fn run(out: &mut impl Write, game: &mut Game) -> io::Result<Option<Solution>> {
+    let mut message = String::new();
+    loop {
+        draw(out, game, &message)?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind == KeyEventKind::Release {
+            continue;
+        }
+        message.clear();
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break,
+            KeyCode::Up | KeyCode::Char('k') => game.move_cursor(-1, 0),
+            KeyCode::Down | KeyCode::Char('j') => game.move_cursor(1, 0),
+            KeyCode::Left | KeyCode::Char('h') => game.move_cursor(0, -1),
+            KeyCode::Right | KeyCode::Char('l') => game.move_cursor(0, 1),
+            KeyCode::Char(' ') | KeyCode::Char('f') => game.toggle(CellState::Filled),
+            KeyCode::Char('x') => game.toggle(CellState::Empty),
+            KeyCode::Delete | KeyCode::Backspace => game.toggle(CellState::Unknown),
+            KeyCode::Char('u') if !game.undo() => message = "nothing to undo".to_string(),
+            KeyCode::Char('r') if !game.redo() => message = "nothing to redo".to_string(),
+            KeyCode::Char('?') => {
+                message = match game.hint() {
+                    Some(reason) => reason,
+                    None => format!("no hint: {}", verdict_message(&game.verify())),
+                };
+            }
+            KeyCode::Char('v') => message = verdict_message(&game.verify()),
+            _ => {}
+        }
+        if game.board.is_complete() && game.verify() == Verdict::Solved {
+            draw(out, game, "solved! press any key")?;
+            event::read()?;
+            return Ok(game.board.to_solution());
+        }
+    }
+    Ok(None)
+}
+
+fn verdict_message(verdict: &Verdict) -> String {
+    match verdict {
+        Verdict::Solved => "solved!".to_string(),
+        Verdict::Violated(lines) => {
+            let lines: Vec<String> = lines
+                .iter()
+                .map(|(var_type, idx)| match var_type {
+                    VarType::Row => format!("row {}", idx + 1),
+                    VarType::Column => format!("column {}", idx + 1),
+                })
+                .collect();
+            format!("violated: {}", lines.join(", "))
+        }
+        Verdict::NoSolution => "there is a mistake somewhere".to_string(),
+        Verdict::Consistent => "no mistakes so far".to_string(),
+        Verdict::TimedOut => "the solver ran out of time".to_string(),
+        Verdict::Invalid(err) => format!("the puzzle is invalid: {}", err),
+    }
+}
+
+fn draw(out: &mut impl Write, game: &Game, message: &str) -> io::Result<()> {
+    let problem = &game.board.problem;
+    let (row_status, col_status) = game.line_status();
+    let clue = |num: i32, status: LineStatus| {
+        let text = format!("{:3}", num);
+        match status {
+            LineStatus::Open => text.stylize(),
+            LineStatus::Complete => text.dark_grey(),
+            LineStatus::Violated => text.red().bold(),
+        }
+    };
+
+    queue!(out, Clear(ClearType::All))?;
+    let mut y = 0;
+    for (r, row_info) in problem.row_info.iter().enumerate() {
+        queue!(out, MoveTo(0, y), Print(" "))?;
+        for (c, cell) in game.board.grid[r].iter().enumerate() {
+            let glyph = cell.glyph();
+            match game.cursor == (r, c) {
+                true => queue!(out, PrintStyledContent(glyph.reverse()))?,
+                false => queue!(out, Print(glyph))?,
+            }
+        }
+        for num in row_info {
+            queue!(out, PrintStyledContent(clue(*num, row_status[r])))?;
+        }
+        y += 1;
+    }
+    y += 1;
+    let clue_rows = problem.col_info.iter().map(|line| line.len()).max();
+    for i in 0..clue_rows.unwrap_or(0) {
+        queue!(out, MoveTo(0, y))?;
+        for (c, col_info) in problem.col_info.iter().enumerate() {
+            match col_info.get(i) {
+                Some(num) => queue!(out, PrintStyledContent(clue(*num, col_status[c])))?,
+                None => queue!(out, Print("   "))?,
+            }
+        }
+        y += 1;
+    }
+    queue!(
+        out,
+        MoveTo(0, y + 1),
+        Print(message),
+        MoveTo(0, y + 2),
+        PrintStyledContent(HELP.dark_grey())
+    )?;
+    out.flush()
+}